@@ -1,4 +1,26 @@
-use crate::utils::{commands, log_message};
+use crate::{
+    assets::{Asset, Metadata},
+    backend::Step,
+    builder,
+    catalog::Catalog,
+    complete::{self, Suggestion},
+    dispatch::{Dispatcher, Job},
+    format::{format_file, normalize},
+    highlight, http,
+    index::{AssetIndex, asset_ids},
+    ipc::Control,
+    library::{self, Route},
+    lint::{Diagnostic, Severity, has_errors, lint},
+    merge::merge,
+    parser::parse,
+    practice::{self, Practice},
+    profile::{Input, Profile},
+    replace::{self, FileHits, Hit, Query},
+    reset,
+    settings::Settings,
+    utils::{log_message, preview},
+    watch::Watch,
+};
 
 #[cfg(target_os = "macos")]
 use {
@@ -9,21 +31,20 @@ use {
         CGEvent, CGEventFlags, CGEventTap, CGEventTapLocation, CGEventTapOptions,
         CGEventTapPlacement, CGEventType, CallbackResult,
     },
+    std::sync::atomic::{AtomicBool, Ordering},
 };
 
 use eframe::egui;
 use egui_file_dialog::FileDialog;
+use interprocess::local_socket::Listener;
 use re_ui;
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
     fs,
     io::Read,
+    ops::Range,
     path::PathBuf,
-    sync::{
-        Arc, Mutex,
-        atomic::{AtomicBool, Ordering},
-        mpsc,
-    },
+    sync::{Arc, Mutex, mpsc},
 };
 #[cfg(target_os = "windows")]
 use {
@@ -32,25 +53,97 @@ use {
     rdev::{Event, EventType},
 };
 
-static IS_SIMULATING: AtomicBool = AtomicBool::new(false);
+const EDITOR_ID: &str = "morph_editor";
 
-fn morph(cmds: &str, delay: u64, log: Arc<Mutex<Vec<String>>>, ctx: &egui::Context) {
-    if cmds.is_empty() {
-        log_message(&log, "Morph commands are empty, skipping.", ctx);
-        return;
-    }
-    log_message(&log, "Starting morph process...", ctx);
-    IS_SIMULATING.store(true, Ordering::SeqCst);
-    commands(cmds, delay, log, ctx);
-    IS_SIMULATING.store(false, Ordering::SeqCst);
+// an open autocomplete popup
+struct Completion {
+    // byte range of txt_cmds the chosen item replaces
+    range: Range<usize>,
+    items: Vec<Suggestion>,
+    selected: usize,
+    // the editor's char cursor it was computed for, moving it closes the popup
+    cursor: usize,
+    pos: egui::Pos2,
+}
+
+struct Preview {
+    steps: Vec<Step>,
+    // what each submitted command does, from the catalog
+    notes: Vec<Vec<String>>,
+    total_ms: u64,
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Default)]
+struct Find {
+    query: Query,
+    // across every morph in the library instead of the editor
+    library: bool,
+    // the library preview, dropped whenever the query changes
+    results: Vec<FileHits>,
+}
+
+// what the file dialog is choosing a file for
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pick {
+    Open,
+    Save,
+    Metadata,
+}
+
+struct Assets {
+    index: AssetIndex,
+    // the "where used" box
+    query: String,
 }
 
 pub struct App {
     txt_cmds: String,
+    catalog: Catalog,
+    metadata: Metadata,
+    // lint results for the editor, refreshed on edits and profile changes
+    diagnostics: Vec<Diagnostic>,
+    // the profile `diagnostics` were computed against
+    linted_with: Option<Profile>,
+    // "Set Morph" was clicked on a morph with errors
+    confirm_arm: bool,
+    // library files "Format all" would change, waiting on confirmation
+    confirm_format: Option<Vec<PathBuf>>,
+    completion: Option<Completion>,
+    // the form view is shown instead of the text editor
+    building: bool,
+    // the last morph the hotkey sent, shared with the dispatcher
+    applied: reset::Applied,
+    // the form's rows and the text they were last in sync with, so a row with a gap in it
+    // survives until it's filled
+    form: Option<(String, Vec<builder::Entry>)>,
+    find: Option<Find>,
+    assets: Option<Assets>,
     cmds: Arc<Mutex<String>>,
     delay: Arc<Mutex<String>>,
+    profiles: Vec<Profile>,
+    profile: Arc<Mutex<Profile>>,
+    preview: Option<Preview>,
+    practice: Arc<Mutex<Practice>>,
+    run_sender: mpsc::Sender<Job>,
     file_dialog: FileDialog,
     file: Option<PathBuf>,
+    // what the open file held when it was last read, to tell edits from disk changes
+    disk_text: String,
+    // newer disk contents waiting on a reload/merge/keep decision
+    conflict: Option<String>,
+    // the open file was deleted, already logged
+    missing: bool,
+    picking: Pick,
+    // every edit is armed right away, no "Set Morph" needed
+    auto_arm: bool,
+    quit_requested: bool,
+    quit_confirmed: bool,
+    watch: Option<Watch>,
+    library: Vec<library::Entry>,
+    library_dir: PathBuf,
+    // player names from the library's morphs, offered by completion
+    library_names: Vec<String>,
     debug_log: Arc<Mutex<Vec<String>>>,
     #[cfg(target_os = "windows")]
     hotkey: Arc<Mutex<KeyBind>>,
@@ -59,12 +152,15 @@ pub struct App {
     is_capturing_hotkey: Arc<Mutex<bool>>,
     hotkey_display_text: String,
     hotkey_text_receiver: mpsc::Receiver<String>,
+    forwarded_receiver: mpsc::Receiver<Vec<String>>,
 }
 
 impl App {
-    pub fn new(cc: &eframe::CreationContext) -> Self {
+    // `listener` is the control socket main claimed, which makes this the only window
+    pub fn new(cc: &eframe::CreationContext, args: Vec<String>, listener: Listener) -> Self {
         let (key_tx, key_rx) = mpsc::channel();
         let (hotkey_text_tx, hotkey_text_rx) = mpsc::channel();
+        let (forwarded_tx, forwarded_rx) = mpsc::channel();
         let debug_log = Arc::new(Mutex::new(Vec::new()));
         let ctx = cc.egui_ctx.clone();
         re_ui::apply_style_and_install_loaders(&ctx);
@@ -77,12 +173,57 @@ impl App {
         );
         cc.egui_ctx.set_style(style);
 
-        let app = Self {
+        // a settings file that doesn't parse is reported, the window still opens with defaults
+        let (settings, settings_error) = match Settings::load(&Settings::path()) {
+            Ok(settings) => (settings, None),
+            Err(error) => (Settings::default(), Some(error)),
+        };
+        let (catalog, catalog_error) = match Catalog::load(&Catalog::path()) {
+            Ok(catalog) => (catalog, None),
+            Err(error) => (Catalog::bundled(), Some(error)),
+        };
+        let (metadata, metadata_error) = match Metadata::load(&Metadata::path()) {
+            Ok(metadata) => (metadata, None),
+            Err(error) => (Metadata::default(), Some(error)),
+        };
+        let library_names =
+            complete::library_names(&library::morph_files(&settings.library_dir()), &catalog);
+        let mut app = Self {
+            catalog,
+            metadata,
+            diagnostics: Vec::new(),
+            linted_with: None,
+            confirm_arm: false,
+            confirm_format: None,
+            completion: None,
+            building: false,
+            applied: Arc::default(),
+            form: None,
+            find: None,
+            assets: None,
             cmds: Arc::new(Mutex::new(String::new())),
             txt_cmds: String::new(),
-            delay: Arc::new(Mutex::new("40".to_owned())),
+            delay: Arc::new(Mutex::new(settings.delay.to_string())),
+            profiles: settings.all_profiles(),
+            profile: Arc::new(Mutex::new(
+                settings.find_profile(&settings.profile).unwrap_or_default(),
+            )),
+            preview: None,
+            practice: Arc::new(Mutex::new(Practice::default())),
+            run_sender: key_tx.clone(),
             file_dialog: FileDialog::new(),
             file: None,
+            disk_text: String::new(),
+            conflict: None,
+            missing: false,
+            picking: Pick::Open,
+            auto_arm: false,
+            quit_requested: false,
+            quit_confirmed: false,
+            watch: None,
+            library: library::entries(&settings.library_dir()),
+            library_dir: settings.library_dir(),
+            library_names,
             debug_log: Arc::clone(&debug_log),
             #[cfg(target_os = "windows")]
             hotkey: Arc::new(Mutex::new(KeyBind::new(Some(KeyCode::RShift), Vec::new()))),
@@ -91,7 +232,27 @@ impl App {
             is_capturing_hotkey: Arc::new(Mutex::new(false)),
             hotkey_display_text: "Right Shift".to_string(),
             hotkey_text_receiver: hotkey_text_rx,
+            forwarded_receiver: forwarded_rx,
+        };
+
+        let control = Control {
+            jobs: key_tx.clone(),
+            library_dir: settings.library_dir(),
+            profile: Arc::clone(&app.profile),
+            catalog: app.catalog.clone(),
+            log: Arc::clone(&app.debug_log),
+            ctx: ctx.clone(),
+            forwarded: Some(forwarded_tx),
+            stop: None,
         };
+        control.clone().serve(listener);
+        if let Some(http_settings) = &settings.http {
+            let message = match http::serve(control, http_settings) {
+                Ok(addr) => format!("HTTP API listening on http://{addr}"),
+                Err(error) => format!("HTTP API unavailable: {error}"),
+            };
+            log_message(&app.debug_log, &message, &ctx);
+        }
 
         app.listen(
             key_tx,
@@ -100,43 +261,814 @@ impl App {
             hotkey_text_tx,
         );
 
-        let cmds_clone = Arc::clone(&app.cmds);
-        let delay_clone = Arc::clone(&app.delay);
-        let log_clone = Arc::clone(&app.debug_log);
-        let logic_ctx = ctx;
+        Dispatcher {
+            cmds: Arc::clone(&app.cmds),
+            delay: Arc::clone(&app.delay),
+            profile: Arc::clone(&app.profile),
+            catalog: app.catalog.clone(),
+            applied: Arc::clone(&app.applied),
+            practice: Some(Arc::clone(&app.practice)),
+            log: Arc::clone(&app.debug_log),
+            ctx: ctx.clone(),
+        }
+        .spawn(key_rx);
 
-        std::thread::spawn(move || {
-            for _ in key_rx {
-                if IS_SIMULATING.load(Ordering::SeqCst) {
-                    log_message(
-                        &log_clone,
-                        "Simulation already in progress, ignoring hotkey.",
-                        &logic_ctx,
-                    );
-                    continue;
+        for error in settings_error
+            .iter()
+            .chain(&catalog_error)
+            .chain(&metadata_error)
+        {
+            log_message(&app.debug_log, error, &ctx);
+        }
+        match Watch::new(settings.library_dir(), ctx.clone()) {
+            Ok(watch) => app.watch = Some(watch),
+            Err(error) => log_message(
+                &app.debug_log,
+                &format!("Not watching for file changes: {error}"),
+                &ctx,
+            ),
+        }
+        app.open_paths(args.into_iter().map(PathBuf::from).collect(), &ctx);
+        app
+    }
+
+    fn save_file(&mut self, path: PathBuf, ctx: &egui::Context) {
+        match fs::write(&path, &self.txt_cmds) {
+            Ok(()) => {
+                self.disk_text = self.txt_cmds.clone();
+                if let Some(watch) = &mut self.watch {
+                    watch.set_file(&path);
                 }
-                let cmds = cmds_clone.lock().unwrap().clone();
-                let delay = delay_clone.lock().unwrap().parse::<u64>().unwrap_or(40);
-                let thread_log = Arc::clone(&log_clone);
-                let thread_ctx = logic_ctx.clone();
-                log_message(
-                    &log_clone,
-                    "Hotkey signal received, spawning a new simulation thread.",
-                    &logic_ctx,
+                log_message(&self.debug_log, &format!("Saved {}.", path.display()), ctx);
+                self.file = Some(path);
+            }
+            Err(error) => log_message(
+                &self.debug_log,
+                &format!("Couldn't save {}: {error}", path.display()),
+                ctx,
+            ),
+        }
+    }
+
+    fn is_armed(&self) -> bool {
+        *self.cmds.lock().unwrap() == self.txt_cmds
+    }
+
+    // text that only lives in the editor would be lost on quit, armed or not
+    fn has_unsaved_changes(&self) -> bool {
+        match self.file {
+            Some(_) => self.txt_cmds != self.disk_text,
+            None => !self.txt_cmds.trim().is_empty(),
+        }
+    }
+
+    fn quit_window(&mut self, ctx: &egui::Context) {
+        if !self.quit_requested {
+            return;
+        }
+        egui::Window::new("Unsaved changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("The editor has changes that aren't saved to a file.");
+                ui.horizontal(|ui| {
+                    if self.file.is_some() && ui.button("Save and quit").clicked() {
+                        if let Some(path) = self.file.clone() {
+                            self.save_file(path, ctx);
+                        }
+                        // stays open if the save failed
+                        self.quit_confirmed = self.txt_cmds == self.disk_text;
+                    }
+                    if ui.button("Quit without saving").clicked() {
+                        self.quit_confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.quit_requested = false;
+                    }
+                });
+            });
+
+        if self.quit_confirmed {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
+
+    // finds the library files formatting would change and asks before touching them
+    fn format_library(&mut self, ctx: &egui::Context) {
+        let mut changed = Vec::new();
+        for entry in &self.library {
+            match format_file(&entry.path, &self.catalog, true) {
+                Ok(true) => changed.push(entry.path.clone()),
+                Ok(false) => {}
+                Err(error) => log_message(&self.debug_log, &error, ctx),
+            }
+        }
+        if changed.is_empty() {
+            log_message(
+                &self.debug_log,
+                "Every library morph is already formatted.",
+                ctx,
+            );
+        } else {
+            self.confirm_format = Some(changed);
+        }
+    }
+
+    fn confirm_format_window(&mut self, ctx: &egui::Context) {
+        let Some(files) = &self.confirm_format else {
+            return;
+        };
+        let mut decided = None;
+        egui::Window::new("Format library morphs?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!("Formatting rewrites {} file(s):", files.len()));
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for file in files {
+                            let shown = file.strip_prefix(&self.library_dir).unwrap_or(file);
+                            ui.monospace(shown.display().to_string());
+                        }
+                    });
+                ui.horizontal(|ui| {
+                    if ui.button("Format").clicked() {
+                        decided = Some(true);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        decided = Some(false);
+                    }
+                });
+            });
+
+        if decided != Some(true) {
+            if decided.is_some() {
+                self.confirm_format = None;
+            }
+            return;
+        }
+        let files = self.confirm_format.take().unwrap_or_default();
+        let mut formatted = 0;
+        for file in &files {
+            match format_file(file, &self.catalog, false) {
+                Ok(changed) => formatted += usize::from(changed),
+                Err(error) => log_message(&self.debug_log, &error, ctx),
+            }
+        }
+        log_message(
+            &self.debug_log,
+            &format!(
+                "Formatted {formatted} of {} library morphs.",
+                self.library.len()
+            ),
+            ctx,
+        );
+    }
+
+    fn relint(&mut self) {
+        let script = parse(&self.txt_cmds);
+        let selected = self.profile.lock().unwrap().clone();
+        let profile = script.apply(&selected);
+        self.diagnostics = lint(&script, &profile, &self.catalog, &self.metadata);
+        self.linted_with = Some(selected);
+    }
+
+    // every change to the editor text goes through here, typed or not
+    fn edited(&mut self) {
+        self.relint();
+        // half-typed lines aren't armed, they'd only fail in-game
+        if self.auto_arm && !has_errors(&self.diagnostics) {
+            *self.cmds.lock().unwrap() = self.txt_cmds.clone();
+        }
+    }
+
+    fn complete(&mut self, editor: &egui::text_edit::TextEditOutput, explicit: bool) {
+        self.completion = None;
+        let Some(cursor) = editor.cursor_range.map(|range| range.primary.ccursor) else {
+            return;
+        };
+        let byte = self
+            .txt_cmds
+            .char_indices()
+            .nth(cursor.index)
+            .map_or(self.txt_cmds.len(), |(byte, _)| byte);
+        let (range, items) = complete::suggestions(
+            &self.txt_cmds,
+            byte,
+            &self.catalog,
+            &self.library_names,
+            explicit,
+        );
+        if items.is_empty() {
+            return;
+        }
+        let pos = editor.galley_pos
+            + editor
+                .galley
+                .pos_from_ccursor(cursor)
+                .left_bottom()
+                .to_vec2();
+        self.completion = Some(Completion {
+            range,
+            items,
+            selected: 0,
+            cursor: cursor.index,
+            pos,
+        });
+    }
+
+    // returns the item that was clicked
+    fn completion_popup(&self, ctx: &egui::Context) -> Option<usize> {
+        let completion = self.completion.as_ref()?;
+        let mut clicked = None;
+        egui::Area::new(egui::Id::new("completion"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(completion.pos)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    egui::ScrollArea::vertical()
+                        .max_height(250.0)
+                        .show(ui, |ui| {
+                            for (i, item) in completion.items.iter().enumerate() {
+                                ui.horizontal(|ui| {
+                                    let selected = i == completion.selected;
+                                    let label = egui::RichText::new(&item.label).monospace();
+                                    let response = ui.selectable_label(selected, label);
+                                    if selected {
+                                        response.scroll_to_me(None);
+                                    }
+                                    if response.clicked() {
+                                        clicked = Some(i);
+                                    }
+                                    ui.weak(&item.detail);
+                                });
+                            }
+                        });
+                });
+            });
+        clicked
+    }
+
+    fn accept_completion(&mut self, index: usize, ctx: &egui::Context) {
+        let Some(completion) = self.completion.take() else {
+            return;
+        };
+        let Some(item) = completion.items.get(index) else {
+            return;
+        };
+        // the text may have been reloaded underneath the popup
+        if self.txt_cmds.get(completion.range.clone()).is_none() {
+            return;
+        }
+        self.txt_cmds
+            .replace_range(completion.range.clone(), &item.insert);
+        let end = completion.range.start + item.insert.len();
+        let cursor = egui::text::CCursor::new(self.txt_cmds[..end].chars().count());
+
+        let id = egui::Id::new(EDITOR_ID);
+        let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+        state
+            .cursor
+            .set_char_range(Some(egui::text::CCursorRange::one(cursor)));
+        state.store(ctx, id);
+        self.edited();
+    }
+
+    fn jump_to_line(&self, ctx: &egui::Context, line: usize) {
+        let id = egui::Id::new(EDITOR_ID);
+        let start: usize = self
+            .txt_cmds
+            .split_inclusive('\n')
+            .take(line)
+            .map(|line| line.chars().count())
+            .sum();
+        let len = self
+            .txt_cmds
+            .lines()
+            .nth(line)
+            .map(|line| line.chars().count())
+            .unwrap_or(0);
+        if let Some(mut state) = egui::TextEdit::load_state(ctx, id) {
+            state
+                .cursor
+                .set_char_range(Some(egui::text::CCursorRange::two(
+                    egui::text::CCursor::new(start),
+                    egui::text::CCursor::new(start + len),
+                )));
+            state.store(ctx, id);
+        }
+        ctx.memory_mut(|memory| memory.request_focus(id));
+    }
+
+    fn text_editor(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let editor_id = egui::Id::new(EDITOR_ID);
+        let focused = ctx.memory(|memory| memory.has_focus(editor_id));
+        let mut accept = None;
+        let mut explicit = false;
+        if focused {
+            ui.input_mut(|i| {
+                explicit = i.consume_key(egui::Modifiers::CTRL, egui::Key::Space);
+                let Some(completion) = &mut self.completion else {
+                    return;
+                };
+                let count = completion.items.len();
+                if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown) {
+                    completion.selected = (completion.selected + 1) % count;
+                }
+                if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp) {
+                    completion.selected = (completion.selected + count - 1) % count;
+                }
+                if i.consume_key(egui::Modifiers::NONE, egui::Key::Enter) {
+                    accept = Some(completion.selected);
+                }
+                if i.consume_key(egui::Modifiers::NONE, egui::Key::Escape) {
+                    self.completion = None;
+                }
+            });
+        }
+        // drawn before the editor so a click on it lands before the editor drops focus
+        if let Some(clicked) = self.completion_popup(ctx) {
+            accept = Some(clicked);
+        }
+        if let Some(index) = accept {
+            self.accept_completion(index, ctx);
+        }
+
+        let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+            let font = egui::FontId::new(16.0, egui::FontFamily::Monospace);
+            let mut job = highlight::layout_job(text, &self.catalog, font, ui.visuals());
+            job.wrap.max_width = wrap_width;
+            ui.fonts(|fonts| fonts.layout_job(job))
+        };
+        let editor = egui::TextEdit::multiline(&mut self.txt_cmds)
+            .font(egui::FontId::new(16.0, egui::FontFamily::Monospace))
+            .desired_width(f32::INFINITY)
+            .desired_rows(20)
+            .id(editor_id)
+            .layouter(&mut layouter)
+            .show(ui);
+        self.paint_hints(ui, &editor);
+
+        if accept.is_some() {
+            ctx.memory_mut(|memory| memory.request_focus(editor_id));
+        } else if editor.response.changed() || explicit {
+            self.edited();
+            self.complete(&editor, explicit);
+        } else if !editor.response.has_focus()
+            || self.completion.as_ref().map(|c| c.cursor)
+                != editor.cursor_range.map(|range| range.primary.ccursor.index)
+        {
+            self.completion = None;
+        }
+    }
+
+    // asset names to the right of the lines that use them
+    fn paint_hints(&self, ui: &egui::Ui, editor: &egui::text_edit::TextEditOutput) {
+        if self.metadata.is_empty() {
+            return;
+        }
+        let mut hints: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+        for (line, _, id) in asset_ids(&self.txt_cmds, &self.catalog) {
+            if let Some(asset) = self.metadata.get(id) {
+                hints.entry(line).or_default().push(&asset.name);
+            }
+        }
+
+        let painter = ui.painter_at(editor.text_clip_rect);
+        let font = egui::FontId::proportional(13.0);
+        let color = ui.visuals().weak_text_color();
+        let rows = editor.galley.rows.len();
+        let mut line = 0;
+        for (i, row) in editor.galley.rows.iter().enumerate() {
+            // a wrapped line only gets its hint after the last row
+            if !row.ends_with_newline && i + 1 < rows {
+                continue;
+            }
+            if let Some(names) = hints.get(&line) {
+                let pos = editor.galley_pos + row.rect.right_center().to_vec2();
+                painter.text(
+                    pos + egui::vec2(16.0, 0.0),
+                    egui::Align2::LEFT_CENTER,
+                    names.join(", "),
+                    font.clone(),
+                    color,
                 );
-                std::thread::spawn(move || {
-                    morph(&cmds, delay, thread_log, &thread_ctx);
+            }
+            line += 1;
+        }
+    }
+
+    fn confirm_arm_window(&mut self, ctx: &egui::Context) {
+        if !self.confirm_arm {
+            return;
+        }
+        let errors = self
+            .diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+        egui::Window::new("Arm a morph with errors?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "The morph has {errors} error(s), see Diagnostics under the editor."
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button("Arm anyway").clicked() {
+                        *self.cmds.lock().unwrap() = self.txt_cmds.clone();
+                        log_message(&self.debug_log, "Armed a morph with errors.", ctx);
+                        self.confirm_arm = false;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.confirm_arm = false;
+                    }
+                });
+            });
+    }
+
+    fn find_window(&mut self, ctx: &egui::Context) {
+        let Some(find) = &mut self.find else {
+            return;
+        };
+        let mut open = true;
+        let mut changed = false;
+        let mut replace_editor = false;
+        let mut search_library = false;
+        let mut apply = false;
+        let mut jump = None;
+        egui::Window::new("Find and replace")
+            .open(&mut open)
+            .default_size([500.0, 400.0])
+            .show(ctx, |ui| {
+                egui::Grid::new("find_fields")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Find:");
+                        let was_id = replace::is_id(&find.query.find);
+                        let typed = ui.text_edit_singleline(&mut find.query.find).changed();
+                        // ids are looked for whole unless unticked
+                        if typed && !was_id && replace::is_id(&find.query.find) {
+                            find.query.whole_word = true;
+                        }
+                        changed |= typed;
+                        ui.end_row();
+                        ui.label("Replace:");
+                        changed |= ui.text_edit_singleline(&mut find.query.replace).changed();
+                        ui.end_row();
+                    });
+                ui.horizontal(|ui| {
+                    changed |= ui.checkbox(&mut find.query.regex, "Regex").changed();
+                    changed |= ui
+                        .checkbox(&mut find.query.match_case, "Match case")
+                        .changed();
+                    changed |= ui
+                        .checkbox(&mut find.query.whole_word, "Whole word")
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    changed |= ui.radio_value(&mut find.library, false, "Editor").changed();
+                    changed |= ui
+                        .radio_value(&mut find.library, true, "Whole library")
+                        .changed();
+                });
+                ui.separator();
+
+                let replacer = match find.query.compile() {
+                    Ok(replacer) => replacer,
+                    Err(error) => {
+                        if !find.query.find.is_empty() {
+                            ui.colored_label(ui.visuals().error_fg_color, error);
+                        }
+                        return;
+                    }
+                };
+                if !find.library {
+                    let hits = replacer.hits(&self.txt_cmds);
+                    ui.label(format!("{} matching line(s)", hits.len()));
+                    replace_editor = ui
+                        .add_enabled(!hits.is_empty(), egui::Button::new("Replace all"))
+                        .clicked();
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for hit in &hits {
+                            if hit_row(ui, hit).clicked() {
+                                jump = Some(hit.line);
+                            }
+                        }
+                    });
+                    return;
+                }
+
+                search_library = ui.button("Search library").clicked();
+                let selected = find.results.iter().filter(|file| file.selected).count();
+                apply = ui
+                    .add_enabled(
+                        selected > 0,
+                        egui::Button::new(format!("Replace in {selected} file(s)")),
+                    )
+                    .clicked();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for file in &mut find.results {
+                        let name = file
+                            .path
+                            .strip_prefix(&self.library_dir)
+                            .unwrap_or(&file.path);
+                        ui.checkbox(
+                            &mut file.selected,
+                            format!("{} ({})", name.display(), file.hits.len()),
+                        );
+                        ui.indent(&file.path, |ui| {
+                            for hit in &file.hits {
+                                hit_row(ui, hit);
+                            }
+                        });
+                    }
                 });
+            });
+
+        if changed {
+            find.results.clear();
+        }
+        let Ok(replacer) = find.query.compile() else {
+            if !open {
+                self.find = None;
+            }
+            return;
+        };
+        if search_library {
+            find.results = replacer.search(&library::morph_files(&self.library_dir));
+            if find.results.is_empty() {
+                log_message(&self.debug_log, "No matches in the library.", ctx);
             }
+        }
+        if apply {
+            let (mut files, mut count) = (0, 0);
+            for file in find.results.iter().filter(|file| file.selected) {
+                match replacer.apply(&file.path) {
+                    Ok(replaced) => {
+                        files += 1;
+                        count += replaced;
+                    }
+                    Err(error) => log_message(&self.debug_log, &error, ctx),
+                }
+            }
+            find.results.clear();
+            log_message(
+                &self.debug_log,
+                &format!("Replaced {count} match(es) in {files} file(s)."),
+                ctx,
+            );
+        }
+        if !open {
+            self.find = None;
+        }
+
+        if replace_editor {
+            let (replaced, count) = replacer.replace(&self.txt_cmds);
+            self.txt_cmds = replaced;
+            self.edited();
+            log_message(
+                &self.debug_log,
+                &format!("Replaced {count} match(es) in the editor."),
+                ctx,
+            );
+        }
+        if let Some(line) = jump {
+            self.jump_to_line(ctx, line);
+        }
+    }
+
+    fn import_metadata(&mut self, path: &std::path::Path, ctx: &egui::Context) {
+        let imported = self.metadata.import(path).and_then(|count| {
+            self.metadata.save(&Metadata::path())?;
+            Ok(count)
         });
+        let message = match imported {
+            Ok(count) => format!(
+                "Imported {count} asset name(s), {} known.",
+                self.metadata.len()
+            ),
+            Err(error) => error,
+        };
+        log_message(&self.debug_log, &message, ctx);
+        // the type checks only cover IDs the metadata knows
+        self.relint();
+    }
 
-        app
+    fn index_library(&self) -> AssetIndex {
+        AssetIndex::build(&library::morph_files(&self.library_dir), &self.catalog)
+    }
+
+    fn assets_window(&mut self, ctx: &egui::Context) {
+        let Some(assets) = &mut self.assets else {
+            return;
+        };
+        let show = |path: &std::path::Path| {
+            let path = path.strip_prefix(&self.library_dir).unwrap_or(path);
+            path.display().to_string()
+        };
+        let name = |id: u64| self.metadata.get(id).map(Asset::label).unwrap_or_default();
+        let mut open = true;
+        let mut picked = None;
+        let mut jump = None;
+        let mut import = false;
+        egui::Window::new("Assets")
+            .open(&mut open)
+            .default_size([450.0, 400.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} asset ID(s) across the library",
+                    assets.index.len()
+                ));
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} named asset(s)", self.metadata.len()));
+                    import = ui
+                        .button("Import names")
+                        .on_hover_text("A JSON export of catalog items with ids, names and types")
+                        .clicked();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Where used:");
+                    ui.text_edit_singleline(&mut assets.query);
+                });
+                if let Ok(id) = assets.query.trim().parse::<u64>() {
+                    let uses = assets.index.where_used(id);
+                    if let Some(asset) = self.metadata.get(id) {
+                        ui.strong(asset.label());
+                    }
+                    if uses.is_empty() {
+                        ui.weak("Not used by any morph");
+                    }
+                    for u in uses {
+                        let text = format!("{}:{}  {}", show(&u.path), u.line + 1, u.command);
+                        if ui.link(text).clicked() {
+                            picked = Some((u.path.clone(), u.line));
+                        }
+                    }
+                }
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let here = asset_ids(&self.txt_cmds, &self.catalog);
+                    egui::CollapsingHeader::new(format!("In the editor ({})", here.len()))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            for (line, command, id) in &here {
+                                let morphs = assets.index.morphs(*id).len();
+                                let text = format!(
+                                    "{:>4}  {command:<12} {id}  ({morphs} morph(s))  {}",
+                                    line + 1,
+                                    name(*id)
+                                );
+                                let text = egui::RichText::new(text).monospace();
+                                if ui.selectable_label(false, text).clicked() {
+                                    jump = Some(*line);
+                                }
+                            }
+                        });
+
+                    let shared = assets.index.shared();
+                    egui::CollapsingHeader::new(format!(
+                        "Shared by several morphs ({})",
+                        shared.len()
+                    ))
+                    .show(ui, |ui| {
+                        for id in shared {
+                            let morphs: Vec<String> =
+                                assets.index.morphs(id).into_iter().map(show).collect();
+                            ui.label(format!("{id} {}: {}", name(id), morphs.join(", ")));
+                        }
+                    });
+
+                    let repeated = assets.index.repeated();
+                    egui::CollapsingHeader::new(format!(
+                        "Repeated within a morph ({})",
+                        repeated.len()
+                    ))
+                    .show(ui, |ui| {
+                        for (id, path) in repeated {
+                            ui.label(format!("{id} in {}", show(path)));
+                        }
+                    });
+                });
+            });
+
+        if !open {
+            self.assets = None;
+        }
+        if import {
+            self.picking = Pick::Metadata;
+            self.file_dialog.pick_file();
+        }
+        if let Some((path, line)) = picked {
+            self.open_file(path, ctx);
+            self.jump_to_line(ctx, line);
+        }
+        if let Some(line) = jump {
+            self.jump_to_line(ctx, line);
+        }
+    }
+
+    fn file_changed(&mut self, ctx: &egui::Context) {
+        let Some(path) = &self.file else {
+            return;
+        };
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            // every later event for the path would repeat it
+            Err(_) if self.missing => return,
+            Err(_) => {
+                log_message(
+                    &self.debug_log,
+                    &format!("{} is gone from disk.", path.display()),
+                    ctx,
+                );
+                self.missing = true;
+                return;
+            }
+        };
+        self.missing = false;
+        if text == self.disk_text {
+            return;
+        }
+
+        if self.conflict.is_none() && self.txt_cmds == self.disk_text {
+            log_message(&self.debug_log, "File changed on disk, reloaded.", ctx);
+            self.txt_cmds = text.clone();
+            self.disk_text = text;
+            self.edited();
+        } else {
+            self.conflict = Some(text);
+        }
+    }
+
+    fn conflict_window(&mut self, ctx: &egui::Context) {
+        let Some(text) = self.conflict.clone() else {
+            return;
+        };
+        let name = self
+            .file
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut choice = None;
+        let mut merged = None;
+        egui::Window::new("File changed on disk")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{name} changed on disk, but the editor has unsaved edits."
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button("Overwrite with disk").clicked() {
+                        choice = Some(text.clone());
+                    }
+                    if ui
+                        .button("Merge")
+                        .on_hover_text(
+                            "Keep the changes from both sides, lines you both changed are marked",
+                        )
+                        .clicked()
+                    {
+                        merged = Some(merge(&self.disk_text, &self.txt_cmds, &text));
+                    }
+                    if ui.button("Keep my edits").clicked() {
+                        choice = Some(self.txt_cmds.clone());
+                    }
+                });
+            });
+
+        if let Some(merged) = merged {
+            if merged.conflicts > 0 {
+                let message = format!(
+                    "{} conflict(s) marked with <<<<<<< in the editor.",
+                    merged.conflicts
+                );
+                log_message(&self.debug_log, &message, ctx);
+            }
+            choice = Some(merged.text);
+        }
+        if let Some(chosen) = choice {
+            self.txt_cmds = chosen;
+            self.disk_text = text;
+            self.conflict = None;
+            self.edited();
+            log_message(&self.debug_log, "File change on disk resolved.", ctx);
+        }
     }
 
     #[cfg(target_os = "macos")]
     fn listen(
         &self,
-        tx: mpsc::Sender<()>,
+        tx: mpsc::Sender<Job>,
         log: Arc<Mutex<Vec<String>>>,
         ctx: egui::Context,
         hotkey_text_sender: mpsc::Sender<String>,
@@ -228,7 +1160,7 @@ impl App {
                         .is_ok()
                     {
                         log_message(&callback_log, "Hotkey PRESSED!", &callback_ctx);
-                        let _ = tx.send(());
+                        let _ = tx.send(Job::Armed);
                     }
                 } else {
                     hotkey_is_down.store(false, Ordering::SeqCst);
@@ -266,10 +1198,49 @@ impl App {
         });
     }
 
+    // one file goes into the editor, several are added to the library
+    fn open_paths(&mut self, paths: Vec<PathBuf>, ctx: &egui::Context) {
+        match library::route(&paths) {
+            Route::Nothing => {}
+            Route::Editor(path) => self.open_file(path, ctx),
+            Route::Library(paths) => {
+                for path in &paths {
+                    let message = match library::add(&self.library_dir, path) {
+                        Ok(target) => format!("Added {} to the library.", target.display()),
+                        Err(error) => error,
+                    };
+                    log_message(&self.debug_log, &message, ctx);
+                }
+            }
+        }
+    }
+
+    fn open_file(&mut self, path: PathBuf, ctx: &egui::Context) {
+        match fs::File::open(&path) {
+            Ok(mut file) => {
+                self.txt_cmds = String::new();
+                let _ = file.read_to_string(&mut self.txt_cmds);
+                self.disk_text = self.txt_cmds.clone();
+                self.conflict = None;
+                if let Some(watch) = &mut self.watch {
+                    watch.set_file(&path);
+                }
+                self.file = Some(path);
+                self.edited();
+                log_message(&self.debug_log, "Read file into textbox.", ctx);
+            }
+            Err(error) => log_message(
+                &self.debug_log,
+                &format!("Couldn't open {}: {error}", path.display()),
+                ctx,
+            ),
+        }
+    }
+
     #[cfg(target_os = "windows")]
     fn listen(
         &self,
-        tx: mpsc::Sender<()>,
+        tx: mpsc::Sender<Job>,
         log: Arc<Mutex<Vec<String>>>,
         ctx: egui::Context,
         hotkey_text_sender: mpsc::Sender<String>,
@@ -309,7 +1280,7 @@ impl App {
                                 .unwrap(),
                             ) {
                                 log_message(&log_clone, "Hotkey PRESSED!", &ctx_clone);
-                                let _ = tx.send(());
+                                let _ = tx.send(Job::Armed);
                             }
                         }
                     }
@@ -345,6 +1316,58 @@ impl eframe::App for App {
         if let Ok(new_text) = self.hotkey_text_receiver.try_recv() {
             self.hotkey_display_text = new_text;
         }
+        if ctx.input(|i| i.viewport().close_requested())
+            && !self.quit_confirmed
+            && self.has_unsaved_changes()
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.quit_requested = true;
+        }
+        while let Ok(args) = self.forwarded_receiver.try_recv() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            log_message(
+                &self.debug_log,
+                "Another launch was forwarded to this window.",
+                ctx,
+            );
+            self.open_paths(args.into_iter().map(PathBuf::from).collect(), ctx);
+        }
+
+        // the profile can also change over the socket, not just from the settings
+        if self.linted_with.as_ref() != Some(&*self.profile.lock().unwrap()) {
+            self.relint();
+        }
+
+        let changes = self
+            .watch
+            .as_ref()
+            .map(|watch| watch.poll())
+            .unwrap_or_default();
+        if changes.library {
+            self.library = library::entries(&self.library_dir);
+            self.library_names =
+                complete::library_names(&library::morph_files(&self.library_dir), &self.catalog);
+            if let Some(query) = self.assets.as_ref().map(|assets| assets.query.clone()) {
+                self.assets = Some(Assets {
+                    index: self.index_library(),
+                    query,
+                });
+            }
+        }
+        if changes.file {
+            self.file_changed(ctx);
+        }
+
+        let dropped: Vec<PathBuf> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|file| file.path.clone())
+                .collect()
+        });
+        if !dropped.is_empty() {
+            self.open_paths(dropped, ctx);
+        }
 
         egui::TopBottomPanel::top("title").show(ctx, |ui| {
             ui.heading("SCP:RP Auto Morpher 🎯");
@@ -362,8 +1385,8 @@ impl eframe::App for App {
                             },
                         );
                         ui.separator();
-                        ui.label("1. Paste morph in textbox, or pick a file.");
-                        ui.label("2. Click 'Set Morph' button.");
+                        ui.label("1. Paste morph in textbox, use the Builder, or pick a file.");
+                        ui.label("2. Click 'Set Morph' button, or tick Auto-arm.");
                         ui.label("3. (Optional) Set a custom hotkey.");
                         ui.label("4. Press the hotkey while in-game.");
                     });
@@ -377,41 +1400,197 @@ impl eframe::App for App {
                         .clicked()
                     {
                         log_message(&self.debug_log, "'Set Morph' button clicked.", ctx);
-                        *self.cmds.lock().unwrap() = self.txt_cmds.clone();
+                        for diagnostic in &self.diagnostics {
+                            log_message(&self.debug_log, &diagnostic.to_string(), ctx);
+                        }
+                        if has_errors(&self.diagnostics) {
+                            self.confirm_arm = true;
+                        } else {
+                            *self.cmds.lock().unwrap() = self.txt_cmds.clone();
+                        }
+                    }
+                    if ui.checkbox(&mut self.auto_arm, "Auto-arm on edit").changed()
+                        && self.auto_arm
+                    {
+                        self.edited();
+                    }
+                    if !self.is_armed() && self.auto_arm && has_errors(&self.diagnostics) {
+                        ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            "Not auto-armed, the editor has errors. The hotkey still runs the last \
+                             armed text",
+                        );
+                    } else if !self.is_armed() {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            "Editor differs from the armed morph",
+                        );
                     }
 
                     ui.add_space(5.0);
 
+                    let script = parse(&self.txt_cmds);
+                    let profile = script.apply(&self.profile.lock().unwrap());
+                    let reset_text = reset::reset(&script, &profile, &self.catalog).join("\n");
                     if ui
                         .add_sized([ui.available_width(), 40.0], egui::Button::new("Reset"))
+                        .on_hover_text(&reset_text)
                         .clicked()
                     {
-                        let reset_text =
-                            "unpermall me\nunpermhats me\nunpermshirt me\nclearstartergear me"
-                                .to_owned();
                         *self.cmds.lock().unwrap() = reset_text;
                         log_message(&self.debug_log, "Commands set to reset.", ctx);
                     }
 
-                    ui.add_space(10.0);
-                    ui.separator();
-                    ui.add_space(10.0);
+                    ui.add_space(5.0);
 
-                    ui.label("File:");
                     if ui
-                        .add_sized([100.0, 30.0], egui::Button::new("Pick File"))
+                        .add_sized([ui.available_width(), 40.0], egui::Button::new("Preview"))
                         .clicked()
                     {
-                        self.file_dialog.pick_file();
+                        let profile = self.profile.lock().unwrap().clone();
+                        let delay = self.delay.lock().unwrap().parse::<u64>().unwrap_or(40);
+                        let script = parse(&self.txt_cmds);
+                        let recording = preview(
+                            &self.txt_cmds,
+                            &profile,
+                            delay,
+                            &self.catalog,
+                            self.applied.lock().unwrap().clone(),
+                        );
+                        let notes = recording
+                            .steps
+                            .iter()
+                            .filter_map(|step| match step {
+                                Step::Paste(text) | Step::Type(text, _) if script.is_run => {
+                                    Some(vec![self.catalog.describe(text)])
+                                }
+                                Step::Paste(text) | Step::Type(text, _) => {
+                                    // only batched submissions hold more than one command
+                                    let commands: Vec<&str> = if profile.batch {
+                                        text.split(profile.separator.as_str()).collect()
+                                    } else {
+                                        vec![text]
+                                    };
+                                    let notes = commands.into_iter().flat_map(|command| {
+                                        std::iter::once(self.catalog.describe(command))
+                                            .chain(self.metadata.describe(command, &self.catalog))
+                                    });
+                                    Some(notes.collect())
+                                }
+                                _ => None,
+                            })
+                            .collect();
+                        self.preview = Some(Preview {
+                            notes,
+                            total_ms: recording.total_ms(),
+                            steps: recording.steps,
+                            diagnostics: self.diagnostics.clone(),
+                        });
                     }
+
+                    ui.add_space(5.0);
+
+                    if ui
+                        .add_sized([ui.available_width(), 40.0], egui::Button::new("Practice"))
+                        .clicked()
+                    {
+                        self.practice.lock().unwrap().open = true;
+                    }
+
+                    ui.add_space(5.0);
+
+                    if ui
+                        .add_sized(
+                            [ui.available_width(), 40.0],
+                            egui::Button::new("Find and replace"),
+                        )
+                        .clicked()
+                    {
+                        self.find.get_or_insert_with(Find::default);
+                    }
+
+                    ui.add_space(5.0);
+
+                    if ui
+                        .add_sized([ui.available_width(), 40.0], egui::Button::new("Assets"))
+                        .clicked()
+                        && self.assets.is_none()
+                    {
+                        self.assets = Some(Assets {
+                            index: self.index_library(),
+                            query: String::new(),
+                        });
+                    }
+
+                    ui.add_space(10.0);
+                    ui.separator();
+                    ui.add_space(10.0);
+
+                    ui.label("File:");
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_sized([100.0, 30.0], egui::Button::new("Pick File"))
+                            .clicked()
+                        {
+                            self.picking = Pick::Open;
+                            self.file_dialog.pick_file();
+                        }
+                        if ui
+                            .add_sized([100.0, 30.0], egui::Button::new("Save"))
+                            .clicked()
+                        {
+                            match self.file.clone() {
+                                Some(path) => self.save_file(path, ctx),
+                                None => {
+                                    self.picking = Pick::Save;
+                                    self.file_dialog.save_file();
+                                }
+                            }
+                        }
+                        if ui
+                            .add_sized([100.0, 30.0], egui::Button::new("Format"))
+                            .clicked()
+                        {
+                            let formatted = normalize(&self.txt_cmds, &self.catalog);
+                            if formatted != self.txt_cmds {
+                                self.txt_cmds = formatted;
+                                self.edited();
+                                log_message(&self.debug_log, "Formatted the morph.", ctx);
+                            }
+                        }
+                    });
                     if let Some(path) = &self.file {
                         if let Some(name) = path.file_name() {
                             ui.label(format!("Chosen: {}", name.to_string_lossy()));
                         }
+                        if self.txt_cmds != self.disk_text {
+                            ui.colored_label(ui.visuals().warn_fg_color, "Unsaved changes");
+                        }
                     } else {
                         ui.label("No file chosen");
                     }
 
+                    let mut picked = None;
+                    let mut format_all = false;
+                    ui.collapsing(format!("Library ({})", self.library.len()), |ui| {
+                        if self.library.is_empty() {
+                            ui.label("Drop several morph files here to add them.");
+                        } else {
+                            format_all = ui.button("Format all").clicked();
+                        }
+                        for entry in &self.library {
+                            if ui.link(&entry.name).clicked() {
+                                picked = Some(entry.path.clone());
+                            }
+                        }
+                    });
+                    if let Some(path) = picked {
+                        self.open_file(path, ctx);
+                    }
+                    if format_all {
+                        self.format_library(ctx);
+                    }
+
                     ui.add_space(10.0);
                     ui.separator();
                     ui.add_space(10.0);
@@ -419,6 +1598,74 @@ impl eframe::App for App {
                     ui.label("Delay (ms):");
                     ui.text_edit_singleline(&mut *self.delay.lock().unwrap());
 
+                    ui.add_space(10.0);
+
+                    ui.label("Profile:");
+                    let mut profile = self.profile.lock().unwrap();
+                    egui::ComboBox::from_id_salt("profile")
+                        .selected_text(format!("{} ({})", profile.name, profile.mode.label()))
+                        .show_ui(ui, |ui| {
+                            for candidate in &self.profiles {
+                                if ui
+                                    .selectable_label(
+                                        candidate.name == profile.name,
+                                        &candidate.name,
+                                    )
+                                    .clicked()
+                                {
+                                    let switched = profile.switch_to(candidate, &self.profiles);
+                                    let kept = switched.input != candidate.input
+                                        || switched.char_delay != candidate.char_delay;
+                                    *profile = switched;
+                                    log_message(
+                                        &self.debug_log,
+                                        &format!("Profile set to {}.", candidate.name),
+                                        ctx,
+                                    );
+                                    if kept {
+                                        log_message(
+                                            &self.debug_log,
+                                            "Kept the input and char delay you set.",
+                                            ctx,
+                                        );
+                                    }
+                                }
+                            }
+                        });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Input:");
+                        for input in [Input::Paste, Input::Type] {
+                            ui.radio_value(&mut profile.input, input, input.label());
+                        }
+                    });
+                    if profile.input == Input::Type {
+                        ui.horizontal(|ui| {
+                            ui.label("Char delay (ms):");
+                            ui.add(egui::DragValue::new(&mut profile.char_delay).range(0..=200));
+                        });
+                    }
+
+                    ui.checkbox(&mut profile.batch, "Batch commands");
+                    if profile.batch {
+                        ui.horizontal(|ui| {
+                            ui.label("Separator:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut profile.separator)
+                                    .desired_width(60.0),
+                            );
+                        });
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Max length:");
+                        ui.add(egui::DragValue::new(&mut profile.max_length).range(20..=1000));
+                    });
+                    ui.checkbox(&mut profile.split_long, "Split long lines").on_hover_text(
+                        "Splits commands like hat that take any number of IDs, others are sent whole",
+                    );
+                    ui.checkbox(&mut profile.reset_first, "Reset before each morph");
+                    drop(profile);
+
                     ui.add_space(10.0);
                     ui.separator();
                     ui.add_space(10.0);
@@ -457,14 +1704,69 @@ impl eframe::App for App {
                 });
             });
 
+        if let Some(preview) = &self.preview {
+            let mut open = true;
+            egui::Window::new("Preview")
+                .open(&mut open)
+                .default_size([500.0, 400.0])
+                .show(ctx, |ui| {
+                    let submissions = preview.steps.iter().filter(|s| s.opens_input()).count();
+                    ui.label(format!(
+                        "{} submission(s), about {:.1} s",
+                        submissions,
+                        preview.total_ms as f64 / 1000.0
+                    ));
+                    for diagnostic in &preview.diagnostics {
+                        ui.colored_label(
+                            severity_color(ui, diagnostic.severity),
+                            diagnostic.to_string(),
+                        );
+                    }
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        let mut elapsed = 0;
+                        let mut submission = 0;
+                        for step in &preview.steps {
+                            if step.opens_input() {
+                                submission += 1;
+                                ui.separator();
+                                ui.strong(format!("Submission {submission}"));
+                                for note in preview.notes.get(submission - 1).into_iter().flatten()
+                                {
+                                    ui.weak(note);
+                                }
+                            }
+                            ui.monospace(format!("{elapsed:>6} ms  {}", step.describe()));
+                            elapsed += step.duration();
+                        }
+                    });
+                });
+            if !open {
+                self.preview = None;
+            }
+        }
+
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::F)) {
+            self.find.get_or_insert_with(Find::default);
+        }
+        self.find_window(ctx);
+        self.assets_window(ctx);
+        self.conflict_window(ctx);
+        self.quit_window(ctx);
+        self.confirm_arm_window(ctx);
+        self.confirm_format_window(ctx);
+
+        if self.practice.lock().unwrap().open && practice::window(ctx, &self.practice) {
+            log_message(&self.debug_log, "Practice run requested.", ctx);
+            let _ = self.run_sender.send(Job::Armed);
+        }
+
         self.file_dialog.update(ctx);
         if let Some(path) = self.file_dialog.take_picked() {
             log_message(&self.debug_log, &format!("File picked: {path:?}"), ctx);
-            self.file = Some(path.to_path_buf());
-            if let Ok(mut file) = fs::File::open(self.file.clone().unwrap()) {
-                self.txt_cmds = String::new();
-                let _ = file.read_to_string(&mut self.txt_cmds);
-                log_message(&self.debug_log, "Read file into textbox.", ctx);
+            match self.picking {
+                Pick::Open => self.open_file(path.to_path_buf(), ctx),
+                Pick::Save => self.save_file(path.to_path_buf(), ctx),
+                Pick::Metadata => self.import_metadata(&path, ctx),
             }
         }
 
@@ -475,11 +1777,54 @@ impl eframe::App for App {
                 ui.separator();
             }
 
-            ui.add(egui::TextEdit::multiline(&mut self.txt_cmds)
-                .font(egui::FontId::new(16.0, egui::FontFamily::Monospace))
-                .desired_width(f32::INFINITY)
-                .desired_rows(20),
-        );
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.building, false, "Text");
+                ui.selectable_value(&mut self.building, true, "Builder");
+            });
+            if !self.building {
+                self.text_editor(ui, ctx);
+            } else {
+                // the text changed elsewhere, the rows are read from it again
+                if self.form.as_ref().is_none_or(|(text, _)| *text != self.txt_cmds) {
+                    self.form = builder::entries(&self.txt_cmds, &self.catalog)
+                        .map(|entries| (self.txt_cmds.clone(), entries));
+                }
+                let mut written = None;
+                match &mut self.form {
+                    Some((text, entries)) => {
+                        if builder::show(ui, entries, &self.catalog, &self.metadata) {
+                            *text = builder::script(entries);
+                            written = Some(text.clone());
+                        }
+                    }
+                    None => {
+                        ui.label("Run scripts can only be edited as text.");
+                    }
+                }
+                if let Some(text) = written {
+                    self.txt_cmds = text;
+                    self.edited();
+                }
+            }
+
+            ui.separator();
+
+            let mut jump = None;
+            egui::CollapsingHeader::new(format!("Diagnostics ({})", self.diagnostics.len()))
+                .default_open(true)
+                .show(ui, |ui| {
+                    for diagnostic in &self.diagnostics {
+                        let text = egui::RichText::new(diagnostic.to_string())
+                            .color(severity_color(ui, diagnostic.severity));
+                        if ui.selectable_label(false, text).clicked() {
+                            jump = Some(diagnostic.line);
+                        }
+                    }
+                });
+            if let Some(line) = jump {
+                self.building = false;
+                self.jump_to_line(ctx, line);
+            }
 
             ui.separator();
 
@@ -495,3 +1840,22 @@ impl eframe::App for App {
         });
     }
 }
+
+// a matching line and what it becomes
+fn hit_row(ui: &mut egui::Ui, hit: &Hit) -> egui::Response {
+    let before = egui::RichText::new(format!("{:>4}  {}", hit.line + 1, hit.before)).monospace();
+    let response = ui.selectable_label(false, before);
+    ui.label(
+        egui::RichText::new(format!("   →  {}", hit.after))
+            .monospace()
+            .strong(),
+    );
+    response
+}
+
+fn severity_color(ui: &egui::Ui, severity: Severity) -> egui::Color32 {
+    match severity {
+        Severity::Warning => ui.visuals().warn_fg_color,
+        Severity::Error => ui.visuals().error_fg_color,
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{
+    catalog::{ArgKind, Catalog},
+    settings::config_dir,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    // the catalog asset type, like Hat, Shirt or HairAccessory
+    #[serde(default, rename = "type")]
+    pub kind: String,
+}
+
+impl Asset {
+    pub fn label(&self) -> String {
+        match self.kind.as_str() {
+            "" => self.name.clone(),
+            kind => format!("{} ({kind})", self.name),
+        }
+    }
+}
+
+// names and types for asset IDs, imported by the user since nothing is looked up online
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Metadata {
+    assets: BTreeMap<u64, Asset>,
+}
+
+impl Metadata {
+    pub fn path() -> PathBuf {
+        config_dir().join("assets.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("Couldn't read {}: {e}", path.display())),
+        };
+        serde_json::from_str(&text).map_err(|e| format!("Couldn't parse {}: {e}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| format!("Couldn't write {}: {e}", path.display()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Asset> {
+        self.assets.get(&id)
+    }
+
+    // names for the known IDs in a command's asset id arguments, for hints next to it
+    pub fn describe(&self, command: &str, catalog: &Catalog) -> Vec<String> {
+        let mut words = command.trim().trim_start_matches(':').split_whitespace();
+        let Some(spec) = words.next().and_then(|name| catalog.find(name)) else {
+            return Vec::new();
+        };
+        words
+            .enumerate()
+            .filter(|(index, _)| spec.arg(*index) == Some(ArgKind::AssetId))
+            .filter_map(|(_, word)| {
+                let id = word.parse().ok()?;
+                Some(format!("{id} {}", self.get(id)?.label()))
+            })
+            .collect()
+    }
+
+    // adds the entries of an export, replacing ones already known, and returns how many
+    pub fn import(&mut self, path: &Path) -> Result<usize, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read {}: {e}", path.display()))?;
+        let json: Value = serde_json::from_str(&text)
+            .map_err(|e| format!("Couldn't parse {}: {e}", path.display()))?;
+
+        let records: Vec<(u64, &Value)> = match &json {
+            // a catalog search response
+            Value::Object(map) if map.get("data").is_some_and(Value::is_array) => {
+                records(&map["data"])
+            }
+            // this store's own layout, keyed by ID
+            Value::Object(map) => map
+                .iter()
+                .filter_map(|(id, record)| Some((id.parse().ok()?, record)))
+                .collect(),
+            list => records(list),
+        };
+        if records.is_empty() {
+            return Err(format!("No assets found in {}", path.display()));
+        }
+
+        for (id, record) in &records {
+            let asset = Asset {
+                name: field(record, &["name", "Name"])
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_owned(),
+                kind: field(record, &["type", "assetType", "AssetType", "assetTypeName"])
+                    .map(asset_type)
+                    .unwrap_or_default(),
+            };
+            self.assets.insert(*id, asset);
+        }
+        Ok(records.len())
+    }
+}
+
+// `[{"id": 1, "name": ..., "assetType": 8}, ...]`, IDs given as numbers or strings
+fn records(list: &Value) -> Vec<(u64, &Value)> {
+    let Some(list) = list.as_array() else {
+        return Vec::new();
+    };
+    list.iter()
+        .filter_map(|record| {
+            let id = field(record, &["id", "Id", "assetId", "AssetId"])?;
+            let id = id.as_u64().or_else(|| id.as_str()?.parse().ok())?;
+            Some((id, record))
+        })
+        .collect()
+}
+
+fn field<'a>(record: &'a Value, names: &[&str]) -> Option<&'a Value> {
+    names.iter().find_map(|name| record.get(name))
+}
+
+// catalog exports give the type as a number
+fn asset_type(value: &Value) -> String {
+    let Some(number) = value.as_u64() else {
+        return value.as_str().unwrap_or_default().to_owned();
+    };
+    let name = match number {
+        2 => "TShirt",
+        8 => "Hat",
+        11 => "Shirt",
+        12 => "Pants",
+        17 => "Head",
+        18 => "Face",
+        19 => "Gear",
+        41 => "HairAccessory",
+        42 => "FaceAccessory",
+        43 => "NeckAccessory",
+        44 => "ShoulderAccessory",
+        45 => "FrontAccessory",
+        46 => "BackAccessory",
+        47 => "WaistAccessory",
+        _ => return number.to_string(),
+    };
+    name.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_asset_id_arguments_are_described() {
+        let mut metadata = Metadata::default();
+        for id in [1, 12345] {
+            let name = format!("Asset {id}");
+            let asset = Asset {
+                name,
+                kind: "Hat".to_owned(),
+            };
+            metadata.assets.insert(id, asset);
+        }
+        let catalog = Catalog::bundled();
+        assert_eq!(
+            metadata.describe(":hat 1 12345", &catalog),
+            ["12345 Asset 12345 (Hat)"]
+        );
+        assert!(metadata.describe("unknown 1 12345", &catalog).is_empty());
+    }
+}
@@ -0,0 +1,380 @@
+use crate::clipboard::ClipboardGuard;
+use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    OpenCommandBar,
+    OpenChat,
+    Erase,
+    Paste(String),
+    Type(String, u64),
+    Enter,
+    // backs out of an input that won't be sent, after a cancel
+    Close,
+    Wait(u64),
+}
+
+impl Step {
+    pub fn duration(&self) -> u64 {
+        match self {
+            Step::Wait(ms) => *ms,
+            Step::Type(text, char_delay) => text.chars().count() as u64 * char_delay,
+            _ => 0,
+        }
+    }
+
+    pub fn opens_input(&self) -> bool {
+        matches!(self, Step::OpenCommandBar | Step::OpenChat)
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Step::OpenCommandBar => "Open command bar (')".to_owned(),
+            Step::OpenChat => "Open chat (/)".to_owned(),
+            Step::Erase => "Erase (Backspace)".to_owned(),
+            Step::Paste(text) => format!("Paste \"{text}\""),
+            Step::Type(text, char_delay) => format!("Type \"{text}\" ({char_delay} ms/char)"),
+            Step::Enter => "Press Enter".to_owned(),
+            Step::Close => "Close (Escape)".to_owned(),
+            Step::Wait(ms) => format!("Wait {ms} ms"),
+        }
+    }
+}
+
+pub trait Backend {
+    fn perform(&mut self, step: &Step);
+
+    fn clipboard_available(&self) -> bool {
+        true
+    }
+
+    fn take_messages(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn restore_clipboard(&mut self) -> bool {
+        false
+    }
+}
+
+pub struct EnigoBackend {
+    enigo: Enigo,
+    clipboard: Option<ClipboardGuard>,
+    messages: Vec<String>,
+    // used when a paste has to fall back to typing
+    char_delay: u64,
+}
+
+impl EnigoBackend {
+    pub fn new(char_delay: u64) -> Self {
+        let enigo = Enigo::new(&Settings::default()).unwrap();
+        Self {
+            enigo,
+            clipboard: ClipboardGuard::new(),
+            messages: Vec::new(),
+            char_delay,
+        }
+    }
+}
+
+impl Backend for EnigoBackend {
+    fn perform(&mut self, step: &Step) {
+        match step {
+            Step::OpenCommandBar => {
+                #[cfg(target_os = "windows")]
+                {
+                    self.enigo.raw(40, Direction::Click).unwrap()
+                }
+                #[cfg(target_os = "macos")]
+                {
+                    self.enigo.raw(39, Direction::Click).unwrap()
+                }
+            }
+            Step::OpenChat => {
+                #[cfg(target_os = "windows")]
+                {
+                    self.enigo.raw(53, Direction::Click).unwrap()
+                }
+                #[cfg(target_os = "macos")]
+                {
+                    self.enigo.raw(44, Direction::Click).unwrap()
+                }
+            }
+            Step::Erase => self.enigo.key(Key::Backspace, Direction::Click).unwrap(),
+            Step::Paste(text) => {
+                let pasted = match self.clipboard.as_mut() {
+                    Some(clipboard) => copy_paste(text, &mut self.enigo, clipboard),
+                    None => false,
+                };
+                if !pasted {
+                    if self.clipboard.is_some() {
+                        self.messages
+                            .push("Clipboard write not verified, typing line instead.".to_owned());
+                    }
+                    type_text(text, self.char_delay, &mut self.enigo);
+                }
+            }
+            Step::Type(text, char_delay) => type_text(text, *char_delay, &mut self.enigo),
+            Step::Enter => self.enigo.key(Key::Return, Direction::Click).unwrap(),
+            Step::Close => self.enigo.key(Key::Escape, Direction::Click).unwrap(),
+            Step::Wait(ms) => std::thread::sleep(std::time::Duration::from_millis(*ms)),
+        }
+    }
+
+    fn clipboard_available(&self) -> bool {
+        self.clipboard.is_some()
+    }
+
+    fn take_messages(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.messages)
+    }
+
+    fn restore_clipboard(&mut self) -> bool {
+        let Some(clipboard) = self.clipboard.as_mut() else {
+            return false;
+        };
+        let restored = clipboard.restore();
+        if clipboard.user_changed() {
+            self.messages
+                .push("Clipboard changed during the run, keeping the newer copy.".to_owned());
+        }
+        restored
+    }
+}
+
+#[derive(Default)]
+pub struct RecordingBackend {
+    pub steps: Vec<Step>,
+}
+
+impl RecordingBackend {
+    pub fn total_ms(&self) -> u64 {
+        self.steps.iter().map(Step::duration).sum()
+    }
+}
+
+impl Backend for RecordingBackend {
+    fn perform(&mut self, step: &Step) {
+        self.steps.push(step.clone());
+    }
+}
+
+fn copy_paste(text: &str, enigo: &mut Enigo, clipboard: &mut ClipboardGuard) -> bool {
+    if !clipboard.set_verified(text) {
+        return false;
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        enigo.key(Key::Control, Direction::Press).unwrap();
+        enigo.key(Key::V, Direction::Click).unwrap();
+        enigo.key(Key::Control, Direction::Release).unwrap();
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        enigo.key(Key::Meta, Direction::Press).unwrap();
+        enigo.key(Key::Other(0x00000009), Direction::Click).unwrap();
+        enigo.key(Key::Meta, Direction::Release).unwrap();
+    }
+
+    true
+}
+
+// newlines go out as Shift+Enter, a plain Enter would submit the text up to there
+fn type_text(text: &str, char_delay: u64, enigo: &mut Enigo) {
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            enigo.key(Key::Shift, Direction::Press).unwrap();
+            enigo.key(Key::Return, Direction::Click).unwrap();
+            enigo.key(Key::Shift, Direction::Release).unwrap();
+        }
+        if char_delay == 0 {
+            if !line.is_empty() {
+                enigo.text(line).unwrap();
+            }
+            continue;
+        }
+
+        let mut buf = [0; 4];
+        for ch in line.chars() {
+            enigo.text(ch.encode_utf8(&mut buf)).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(char_delay));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        catalog::Catalog,
+        parser::{Script, parse},
+        profile::{Input, Mode, Profile},
+        reset::FALLBACK,
+        utils::{CANCEL, commands},
+    };
+    use eframe::egui;
+    use std::sync::{Arc, Mutex, atomic::Ordering};
+
+    // CANCEL is global, runs take turns so one test's cancel can't stop another's
+    static RUNS: Mutex<()> = Mutex::new(());
+
+    fn record(cmds: &str, profile: &Profile) -> Vec<Step> {
+        let mut backend = RecordingBackend::default();
+        run(cmds, profile, &mut backend);
+        backend.steps
+    }
+
+    fn run(cmds: &str, profile: &Profile, backend: &mut dyn Backend) {
+        run_after(cmds, profile, &Mutex::new(None), backend);
+    }
+
+    fn run_after(
+        cmds: &str,
+        profile: &Profile,
+        applied: &Mutex<Option<Script>>,
+        backend: &mut dyn Backend,
+    ) {
+        let _turn = RUNS.lock().unwrap_or_else(|e| e.into_inner());
+        let log = Arc::new(Mutex::new(Vec::new()));
+        commands(
+            cmds,
+            profile,
+            40,
+            &Catalog::bundled(),
+            applied,
+            backend,
+            log,
+            &egui::Context::default(),
+        );
+        CANCEL.store(false, Ordering::SeqCst);
+    }
+
+    // cancels as soon as the text is in the bar
+    #[derive(Default)]
+    struct CancelAfterPaste(RecordingBackend);
+
+    impl Backend for CancelAfterPaste {
+        fn perform(&mut self, step: &Step) {
+            self.0.perform(step);
+            if matches!(step, Step::Paste(_)) {
+                CANCEL.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+
+    fn command_bar(text: &str) -> Vec<Step> {
+        vec![
+            Step::OpenCommandBar,
+            Step::Wait(40),
+            Step::Erase,
+            Step::Wait(20),
+            Step::Paste(text.to_owned()),
+            Step::Wait(40),
+            Step::Enter,
+            Step::Wait(40),
+        ]
+    }
+
+    fn chat(text: &str) -> Vec<Step> {
+        vec![
+            Step::OpenChat,
+            Step::Wait(40),
+            Step::Paste(text.to_owned()),
+            Step::Wait(40),
+            Step::Enter,
+            Step::Wait(40),
+        ]
+    }
+
+    #[test]
+    fn command_bar_drops_prefix_and_comments() {
+        let steps = record(
+            "# a comment\n:hat me 1\n\nshirt me 2\n",
+            &Profile::default(),
+        );
+        let expected = [command_bar("hat me 1"), command_bar("shirt me 2")].concat();
+        assert_eq!(steps, expected);
+    }
+
+    #[test]
+    fn chat_adds_prefix() {
+        let profile = Profile {
+            mode: Mode::Chat,
+            ..Profile::default()
+        };
+        let steps = record("hat me 1\n:shirt me 2\n", &profile);
+        let expected = [chat(":hat me 1"), chat(":shirt me 2")].concat();
+        assert_eq!(steps, expected);
+    }
+
+    #[test]
+    fn mode_directive_overrides_profile() {
+        let steps = record("#mode chat\nhat me 1\n", &Profile::default());
+        assert_eq!(steps, chat(":hat me 1"));
+    }
+
+    #[test]
+    fn run_script_is_one_submission() {
+        let steps = record("run\nprint(1)\nprint(2)\n", &Profile::default());
+        assert_eq!(steps, command_bar("run\nprint(1)\nprint(2)"));
+
+        let script = parse(":run print(1)\nhat me 1");
+        assert!(script.is_run);
+        assert_eq!(script.commands.len(), 1);
+    }
+
+    #[test]
+    fn run_script_is_pasted_in_type_mode() {
+        let profile = Profile {
+            input: Input::Type,
+            ..Profile::default()
+        };
+        let steps = record("run\nprint(1)\nprint(2)\n", &profile);
+        assert_eq!(steps, command_bar("run\nprint(1)\nprint(2)"));
+
+        let steps = record("hat me 1\n", &profile);
+        assert_eq!(
+            steps[4],
+            Step::Type("hat me 1".to_owned(), profile.char_delay)
+        );
+    }
+
+    #[test]
+    fn run_must_be_its_own_word() {
+        let script = parse("runspeed me 2\nhat me 1");
+        assert!(!script.is_run);
+        assert_eq!(script.commands.len(), 2);
+        assert!(!parse("hat me 1\nrun print(1)").is_run);
+    }
+
+    #[test]
+    fn cancel_closes_an_open_bar() {
+        let mut backend = CancelAfterPaste::default();
+        run("hat me 1\nshirt me 2\n", &Profile::default(), &mut backend);
+        let mut expected = command_bar("hat me 1");
+        expected.truncate(5);
+        expected.push(Step::Close);
+        assert_eq!(backend.0.steps, expected);
+    }
+
+    #[test]
+    fn reset_first_undoes_the_previous_run() {
+        let profile = Profile {
+            reset_first: true,
+            ..Profile::default()
+        };
+        let applied = Mutex::new(None);
+        let mut first = RecordingBackend::default();
+        run_after("hat me 1\n", &profile, &applied, &mut first);
+        assert_eq!(first.steps.len(), (FALLBACK.lines().count() + 1) * 8);
+
+        let mut second = RecordingBackend::default();
+        run_after("shirt me 2\n", &profile, &applied, &mut second);
+        let expected = [command_bar("removehats me"), command_bar("shirt me 2")].concat();
+        assert_eq!(second.steps, expected);
+    }
+}
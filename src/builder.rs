@@ -0,0 +1,432 @@
+use crate::{
+    assets::Metadata,
+    catalog::{ArgKind, Catalog},
+    parser::parse,
+};
+use eframe::egui;
+
+// the parts of an avatar the form has rows for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slot {
+    Character,
+    Shirt,
+    Pants,
+    Face,
+    Accessories,
+    Scale,
+    Color,
+}
+
+impl Slot {
+    pub const ALL: [Slot; 7] = [
+        Slot::Character,
+        Slot::Shirt,
+        Slot::Pants,
+        Slot::Face,
+        Slot::Accessories,
+        Slot::Scale,
+        Slot::Color,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Slot::Character => "Character",
+            Slot::Shirt => "Shirt",
+            Slot::Pants => "Pants",
+            Slot::Face => "Face",
+            Slot::Accessories => "Accessories",
+            Slot::Scale => "Scale",
+            Slot::Color => "Color",
+        }
+    }
+
+    // the catalog commands that fill the slot, a new row starts with the first
+    pub fn commands(self) -> &'static [&'static str] {
+        match self {
+            Slot::Character => &["char", "name"],
+            Slot::Shirt => &["shirt", "permshirt"],
+            Slot::Pants => &["pants", "permpants"],
+            Slot::Face => &["face"],
+            Slot::Accessories => &["hat", "permhat"],
+            Slot::Scale => &["size"],
+            Slot::Color => &["bodycolor"],
+        }
+    }
+
+    pub fn of(command: &str) -> Option<Slot> {
+        Slot::ALL.into_iter().find(|slot| {
+            slot.commands()
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(command))
+        })
+    }
+
+    fn entry(self, catalog: &Catalog) -> Entry {
+        let name = self.commands()[0];
+        let fields = catalog
+            .find(name)
+            .map_or(1, |spec| spec.args.len().saturating_sub(1));
+        Entry::Command {
+            slot: self,
+            name: name.to_owned(),
+            target: "me".to_owned(),
+            args: vec![String::new(); fields],
+            source: None,
+            edited: true,
+        }
+    }
+}
+
+// one line of the morph, either a row of the form or text the form leaves alone
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Entry {
+    Command {
+        slot: Slot,
+        name: String,
+        target: String,
+        args: Vec<String>,
+        // the line the row was read from, written back as is until the row is edited
+        source: Option<String>,
+        edited: bool,
+    },
+    Raw(String),
+}
+
+impl Entry {
+    // an empty field before a filled one, writing it would shift the later ones into its place
+    pub fn has_gap(&self) -> bool {
+        let Entry::Command { target, args, .. } = self else {
+            return false;
+        };
+        let words: Vec<&String> = std::iter::once(target).chain(args).collect();
+        words
+            .iter()
+            .position(|word| word.is_empty())
+            .is_some_and(|empty| words[empty..].iter().any(|word| !word.is_empty()))
+    }
+}
+
+// None for run scripts, which are code rather than rows
+pub fn entries(text: &str, catalog: &Catalog) -> Option<Vec<Entry>> {
+    if parse(text).is_run {
+        return None;
+    }
+    Some(text.lines().map(|line| entry(line, catalog)).collect())
+}
+
+fn entry(source: &str, catalog: &Catalog) -> Entry {
+    let line = source.trim();
+    let mut words = line.trim_start_matches(':').split_whitespace();
+    let Some(spec) = words.next().and_then(|name| catalog.find(name)) else {
+        return Entry::Raw(source.to_owned());
+    };
+    let Some(slot) = Slot::of(&spec.name) else {
+        return Entry::Raw(source.to_owned());
+    };
+
+    let target = words.next().unwrap_or_default().to_owned();
+    let mut args: Vec<String> = match spec.args.last() {
+        Some(ArgKind::Text) => vec![words.collect::<Vec<_>>().join(" ")],
+        _ => words.map(str::to_owned).collect(),
+    };
+    let fields = spec.args.len().saturating_sub(1);
+    // extra arguments the form has no field for stay visible as text
+    if args.len() > fields && !spec.repeat {
+        return Entry::Raw(source.to_owned());
+    }
+    args.resize(args.len().max(fields), String::new());
+    Entry::Command {
+        slot,
+        name: spec.name.clone(),
+        target,
+        args,
+        source: Some(source.to_owned()),
+        edited: false,
+    }
+}
+
+// rows with a gap keep the line they were read from, or aren't written yet if they're new
+pub fn script(entries: &[Entry]) -> String {
+    let lines: Vec<String> = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            Entry::Command { source, .. } if entry.has_gap() => source.clone(),
+            Entry::Command {
+                source: Some(source),
+                edited: false,
+                ..
+            } => Some(source.clone()),
+            Entry::Command {
+                name, target, args, ..
+            } => Some(
+                std::iter::once(name)
+                    .chain([target])
+                    .chain(args)
+                    .filter(|word| !word.is_empty())
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            Entry::Raw(line) => Some(line.clone()),
+        })
+        .collect();
+    if lines.is_empty() {
+        return String::new();
+    }
+    lines.join("\n") + "\n"
+}
+
+enum Action {
+    Up,
+    Down,
+    Remove,
+}
+
+// the form view of a morph, returns true when anything changed
+pub fn show(
+    ui: &mut egui::Ui,
+    entries: &mut Vec<Entry>,
+    catalog: &Catalog,
+    metadata: &Metadata,
+) -> bool {
+    let mut changed = false;
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Add:");
+        for slot in Slot::ALL {
+            if ui.button(slot.label()).clicked() {
+                entries.push(slot.entry(catalog));
+                changed = true;
+            }
+        }
+    });
+    ui.separator();
+
+    let gaps: Vec<usize> = (0..entries.len())
+        .filter(|&i| entries[i].has_gap())
+        .map(|i| i + 1)
+        .collect();
+    if !gaps.is_empty() {
+        ui.colored_label(
+            ui.visuals().error_fg_color,
+            format!(
+                "Row(s) {gaps:?} have an empty field before a filled one and aren't written to the \
+                 text until it's filled"
+            ),
+        );
+    }
+
+    let mut action = None;
+    egui::ScrollArea::vertical()
+        .id_salt("builder")
+        .max_height(400.0)
+        .show(ui, |ui| {
+            if entries.is_empty() {
+                ui.weak("Add a row to start a morph.");
+            }
+            for (i, entry) in entries.iter_mut().enumerate() {
+                ui.push_id(i, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("⬆").clicked() {
+                            action = Some((i, Action::Up));
+                        }
+                        if ui.small_button("⬇").clicked() {
+                            action = Some((i, Action::Down));
+                        }
+                        if ui.small_button("🗑").clicked() {
+                            action = Some((i, Action::Remove));
+                        }
+                        changed |= row(ui, entry, catalog, metadata);
+                    });
+                });
+            }
+        });
+
+    match action {
+        Some((i, Action::Up)) if i > 0 => entries.swap(i, i - 1),
+        Some((i, Action::Down)) if i + 1 < entries.len() => entries.swap(i, i + 1),
+        Some((i, Action::Remove)) => {
+            entries.remove(i);
+        }
+        _ => return changed,
+    }
+    true
+}
+
+fn row(ui: &mut egui::Ui, entry: &mut Entry, catalog: &Catalog, metadata: &Metadata) -> bool {
+    let (slot, name, target, args, edited) = match entry {
+        Entry::Raw(line) => {
+            let edit = egui::TextEdit::singleline(line)
+                .font(egui::TextStyle::Monospace)
+                .hint_text("blank line")
+                .desired_width(f32::INFINITY);
+            return ui.add(edit).changed();
+        }
+        Entry::Command {
+            slot,
+            name,
+            target,
+            args,
+            edited,
+            ..
+        } => (*slot, name, target, args, edited),
+    };
+
+    let mut changed = false;
+    let label = egui::Label::new(egui::RichText::new(slot.label()).strong());
+    ui.add_sized([80.0, 18.0], label);
+    if slot.commands().len() > 1 {
+        egui::ComboBox::from_id_salt("command")
+            .selected_text(name.as_str())
+            .show_ui(ui, |ui| {
+                for command in slot.commands() {
+                    changed |= ui
+                        .selectable_value(name, command.to_string(), *command)
+                        .changed();
+                }
+            });
+    }
+    changed |= field(ui, ArgKind::Player, target, metadata);
+
+    let Some(spec) = catalog.find(name) else {
+        *edited |= changed;
+        return changed;
+    };
+    let several = args.len() > 1;
+    let mut remove = None;
+    for (index, arg) in args.iter_mut().enumerate() {
+        let kind = spec.arg(index + 1).unwrap_or(ArgKind::Text);
+        changed |= field(ui, kind, arg, metadata);
+        if spec.repeat && several && ui.small_button("✕").clicked() {
+            remove = Some(index);
+        }
+    }
+    if let Some(index) = remove {
+        args.remove(index);
+        changed = true;
+    }
+    if spec.repeat && ui.small_button("+").clicked() {
+        args.push(String::new());
+        changed = true;
+    }
+    *edited |= changed;
+    changed
+}
+
+fn field(ui: &mut egui::Ui, kind: ArgKind, value: &mut String, metadata: &Metadata) -> bool {
+    let width = match kind {
+        ArgKind::Text => 160.0,
+        ArgKind::AssetId => 110.0,
+        ArgKind::Color => 90.0,
+        ArgKind::Player | ArgKind::Number => 60.0,
+    };
+    let edit = egui::TextEdit::singleline(value)
+        .hint_text(kind.label())
+        .desired_width(width);
+    let mut changed = ui.add(edit).changed();
+    // a space would split the field into two arguments, only text may have them
+    if changed && kind != ArgKind::Text {
+        value.retain(|c| !c.is_whitespace());
+    }
+
+    match kind {
+        ArgKind::AssetId => {
+            if let Some(asset) = value.parse().ok().and_then(|id| metadata.get(id)) {
+                ui.weak(asset.label());
+            }
+        }
+        ArgKind::Color => {
+            let mut rgb = rgb(value).unwrap_or([255, 255, 255]);
+            if egui::color_picker::color_edit_button_srgb(ui, &mut rgb).changed() {
+                *value = format!("{},{},{}", rgb[0], rgb[1], rgb[2]);
+                changed = true;
+            }
+        }
+        _ => {}
+    }
+    changed
+}
+
+fn rgb(value: &str) -> Option<[u8; 3]> {
+    if let Some(hex) = value.strip_prefix('#').filter(|hex| hex.len() == 6) {
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some([channel(0)?, channel(2)?, channel(4)?]);
+    }
+    let parts: Vec<u8> = value
+        .split(',')
+        .map(|part| part.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    parts.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MORPH: &str = "\
+#mode chat
+:HAT  me 1 2
+  shirt me 3
+
+-- a comment
+permhats me 4
+bodycolor me #ff0000
+unpermall me
+";
+
+    fn rows(text: &str) -> Vec<Entry> {
+        entries(text, &Catalog::bundled()).unwrap()
+    }
+
+    fn edit(entry: &mut Entry, change: impl FnOnce(&mut String, &mut Vec<String>)) {
+        let Entry::Command {
+            target,
+            args,
+            edited,
+            ..
+        } = entry
+        else {
+            panic!("not a row: {entry:?}");
+        };
+        change(target, args);
+        *edited = true;
+    }
+
+    #[test]
+    fn untouched_rows_are_written_back_as_they_were() {
+        let entries = rows(MORPH);
+        assert!(matches!(
+            entries[1],
+            Entry::Command {
+                slot: Slot::Accessories,
+                ..
+            }
+        ));
+        assert_eq!(script(&entries), MORPH);
+        assert_eq!(entries, rows(&script(&entries)));
+    }
+
+    #[test]
+    fn edited_rows_are_written_in_full() {
+        let mut entries = rows(MORPH);
+        edit(&mut entries[2], |_, args| args[0] = "30".to_owned());
+        let text = script(&entries);
+        assert_eq!(text.lines().nth(2), Some("shirt me 30"));
+        assert_eq!(text.lines().nth(1), Some(":HAT  me 1 2"));
+    }
+
+    #[test]
+    fn rows_with_a_gap_keep_their_line() {
+        let mut entries = rows(MORPH);
+        edit(&mut entries[2], |target, _| target.clear());
+        assert!(entries[2].has_gap());
+        assert_eq!(script(&entries), MORPH);
+
+        // a new row isn't written until its gap is filled
+        let mut fresh = Slot::Shirt.entry(&Catalog::bundled());
+        edit(&mut fresh, |target, args| {
+            target.clear();
+            args[0] = "5".to_owned();
+        });
+        assert_eq!(script(&[fresh]), "");
+    }
+}
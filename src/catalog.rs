@@ -0,0 +1,202 @@
+use crate::settings::config_dir;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+const BUNDLED: &str = include_str!("../assets/commands.toml");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArgKind {
+    Player,
+    AssetId,
+    Number,
+    Color,
+    Text,
+}
+
+impl ArgKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ArgKind::Player => "player",
+            ArgKind::AssetId => "asset id",
+            ArgKind::Number => "number",
+            ArgKind::Color => "color",
+            ArgKind::Text => "text",
+        }
+    }
+
+    pub fn accepts(self, word: &str) -> bool {
+        match self {
+            ArgKind::Player | ArgKind::Text => !word.is_empty(),
+            ArgKind::AssetId => !word.is_empty() && word.chars().all(|c| c.is_ascii_digit()),
+            ArgKind::Number => word.parse::<f64>().is_ok(),
+            // a name like `red`, `255,0,0` or `#ff0000`
+            ArgKind::Color => {
+                word.chars().all(|c| c.is_alphabetic())
+                    || word.split(',').all(|part| part.parse::<u8>().is_ok())
+                    || word.strip_prefix('#').is_some_and(|hex| {
+                        matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit())
+                    })
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Spec {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub args: Vec<ArgKind>,
+    #[serde(default)]
+    pub repeat: bool,
+    #[serde(default)]
+    pub reversible: bool,
+    #[serde(default)]
+    pub undo: Option<String>,
+    // the asset types its IDs should be, checked against imported metadata
+    #[serde(default)]
+    pub asset_types: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl Spec {
+    // the kind expected at argument `index`, after the command name
+    pub fn arg(&self, index: usize) -> Option<ArgKind> {
+        let last = self.args.last().copied();
+        match self.args.get(index) {
+            Some(kind) => Some(*kind),
+            None if self.repeat || last == Some(ArgKind::Text) => last,
+            None => None,
+        }
+    }
+
+    pub fn takes_type(&self, kind: &str) -> bool {
+        self.asset_types.is_empty()
+            || self
+                .asset_types
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(kind))
+    }
+
+    pub fn usage(&self) -> String {
+        let mut usage = self.name.clone();
+        for kind in &self.args {
+            usage.push_str(&format!(" <{}>", kind.label()));
+        }
+        if self.repeat {
+            usage.push_str("...");
+        }
+        usage
+    }
+}
+
+// a named block of lines offered by autocomplete
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snippet {
+    pub name: String,
+    pub body: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Default, Deserialize)]
+struct File {
+    #[serde(default)]
+    command: Vec<Spec>,
+    #[serde(default)]
+    snippet: Vec<Snippet>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Catalog {
+    pub commands: Vec<Spec>,
+    pub snippets: Vec<Snippet>,
+}
+
+impl Catalog {
+    pub fn bundled() -> Self {
+        let file: File = toml::from_str(BUNDLED).expect("bundled commands.toml is invalid");
+        Self {
+            commands: file.command,
+            snippets: file.snippet,
+        }
+    }
+
+    pub fn path() -> PathBuf {
+        config_dir().join("commands.toml")
+    }
+
+    // the bundled entries plus the user's, which replace bundled ones of the same name
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut catalog = Self::bundled();
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(catalog),
+            Err(e) => return Err(format!("Couldn't read {}: {e}", path.display())),
+        };
+        let file: File =
+            toml::from_str(&text).map_err(|e| format!("Couldn't parse {}: {e}", path.display()))?;
+
+        for spec in file.command {
+            catalog
+                .commands
+                .retain(|existing| !existing.name.eq_ignore_ascii_case(&spec.name));
+            catalog.commands.push(spec);
+        }
+        for snippet in file.snippet {
+            catalog
+                .snippets
+                .retain(|existing| !existing.name.eq_ignore_ascii_case(&snippet.name));
+            catalog.snippets.push(snippet);
+        }
+        Ok(catalog)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Spec> {
+        self.commands.iter().find(|spec| {
+            spec.name.eq_ignore_ascii_case(name)
+                || spec
+                    .aliases
+                    .iter()
+                    .any(|alias| alias.eq_ignore_ascii_case(name))
+        })
+    }
+
+    // one line about a submitted command, for the preview
+    pub fn describe(&self, command: &str) -> String {
+        let command = command.trim().trim_start_matches(':');
+        let name = command.split_whitespace().next().unwrap_or_default();
+        match self.find(name) {
+            Some(spec) if spec.reversible => format!(
+                "{}: {} (undo with {})",
+                spec.usage(),
+                spec.description,
+                spec.undo.as_deref().unwrap_or("a respawn")
+            ),
+            Some(spec) => format!("{}: {}", spec.usage(), spec.description),
+            None => format!("{name}: not in the command catalog"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_by_name_rgb_or_hex() {
+        for color in ["red", "255,0,0", "#ff0000", "#F00"] {
+            assert!(ArgKind::Color.accepts(color), "{color}");
+        }
+        for color in ["256,0,0", "#ff00", "#gg0000", "ff0000"] {
+            assert!(!ArgKind::Color.accepts(color), "{color}");
+        }
+    }
+}
@@ -0,0 +1,438 @@
+use crate::{
+    assets::Metadata,
+    backend::{EnigoBackend, Step},
+    catalog::Catalog,
+    daemon,
+    format::format_file,
+    index::{AssetIndex, asset_ids},
+    library::{self, morph_files},
+    lint::{Severity, has_errors, lint},
+    parser::parse,
+    profile::Profile,
+    settings::Settings,
+    utils::{commands, preview, subscribe_log},
+};
+use eframe::egui;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const USAGE: &str = "\
+Usage:
+  auto_morph                      open the window
+  auto_morph run <file> [--delay MS] [--countdown S] [--profile NAME] [--force]
+  auto_morph validate <file|dir> [--profile NAME]
+  auto_morph fmt <file|dir> [--check]   rewrite morphs in the standard style
+  auto_morph assets                     list the asset IDs used in the library
+  auto_morph assets where <id>          which morphs use an asset ID
+  auto_morph assets morph <name|file>   the asset IDs in one morph
+  auto_morph assets unused <file>       IDs listed in a file that no morph uses
+  auto_morph assets import <file>       add asset names and types from a JSON export
+  auto_morph preview <file> [--delay MS] [--profile NAME] [--json]
+  auto_morph daemon [--settings FILE]   run hotkey bindings without a window
+  auto_morph stop                       stop a running daemon";
+
+struct Options {
+    delay: u64,
+    countdown: u64,
+    profile: Profile,
+    json: bool,
+    force: bool,
+    check: bool,
+    paths: Vec<PathBuf>,
+}
+
+// returns None when the arguments are files for the window to open
+pub fn main(args: &[String]) -> Option<i32> {
+    let (command, rest) = args.split_first()?;
+    let result = match command.as_str() {
+        "run" => parse_options(rest).and_then(run),
+        "validate" => parse_options(rest).and_then(validate_paths),
+        "fmt" => parse_options(rest).and_then(format_paths),
+        "assets" => assets(rest),
+        "preview" => parse_options(rest).and_then(preview_file),
+        "daemon" => match rest {
+            [] => daemon::run(&Settings::path()),
+            [flag, path] if flag == "--settings" => daemon::run(Path::new(path)),
+            _ => Err("daemon only takes --settings FILE".to_owned()),
+        },
+        "stop" => daemon::request_stop(),
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            Ok(0)
+        }
+        path if Path::new(path).exists() => return None,
+        unknown => Err(format!("Unknown command {unknown}")),
+    };
+
+    Some(match result {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("{error}\n\n{USAGE}");
+            2
+        }
+    })
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let settings = Settings::load(&Settings::path())?;
+    let mut options = Options {
+        delay: settings.delay,
+        countdown: 3,
+        profile: settings.find_profile(&settings.profile).unwrap_or_default(),
+        json: false,
+        force: false,
+        check: false,
+        paths: Vec::new(),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--delay" => options.delay = number(args.next(), arg)?,
+            "--countdown" => options.countdown = number(args.next(), arg)?,
+            "--profile" => {
+                let name = args.next().ok_or("--profile needs a name")?;
+                options.profile = find_profile(name)?;
+            }
+            "--json" => options.json = true,
+            "--force" => options.force = true,
+            "--check" => options.check = true,
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {flag}")),
+            path => options.paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if options.paths.is_empty() {
+        return Err("Missing a morph file".to_owned());
+    }
+    Ok(options)
+}
+
+fn number(value: Option<&String>, flag: &str) -> Result<u64, String> {
+    value
+        .and_then(|v| v.parse().ok())
+        .ok_or(format!("{flag} needs a number"))
+}
+
+fn find_profile(name: &str) -> Result<Profile, String> {
+    let settings = Settings::load(&Settings::path())?;
+    settings.find_profile(name).ok_or_else(|| {
+        let profiles = settings.all_profiles();
+        let names: Vec<&str> = profiles.iter().map(|p| p.name.as_str()).collect();
+        format!(
+            "Unknown profile {name}, expected one of: {}",
+            names.join(", ")
+        )
+    })
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {e}", path.display()))
+}
+
+// run and preview take a single morph, a second path is a mistake rather than a queue
+fn one_file(paths: &[PathBuf]) -> Result<&Path, String> {
+    match paths {
+        [path] => Ok(path),
+        _ => Err(format!("Expected one morph file, got {}", paths.len())),
+    }
+}
+
+// unlike the listings, this refuses a store that doesn't load, saving over it would lose it
+fn import(file: &Path) -> Result<i32, String> {
+    let mut metadata = Metadata::load(&Metadata::path())?;
+    let count = metadata.import(file)?;
+    metadata.save(&Metadata::path())?;
+    println!("Imported {count} asset(s), {} known", metadata.len());
+    Ok(0)
+}
+
+// asset names only label output, so a broken store is warned about and left out
+fn metadata() -> Metadata {
+    Metadata::load(&Metadata::path()).unwrap_or_else(|error| {
+        eprintln!("warning: {error}, going on without asset names");
+        Metadata::default()
+    })
+}
+
+fn run(options: Options) -> Result<i32, String> {
+    let cmds = read(one_file(&options.paths)?)?;
+    let script = parse(&cmds);
+    if script.commands.is_empty() {
+        return Err("Morph commands are empty".to_owned());
+    }
+    let catalog = Catalog::load(&Catalog::path())?;
+    let metadata = metadata();
+    let diagnostics = lint(
+        &script,
+        &script.apply(&options.profile),
+        &catalog,
+        &metadata,
+    );
+    for diagnostic in &diagnostics {
+        println!("{diagnostic}");
+    }
+    if has_errors(&diagnostics) && !options.force {
+        return Err("The morph has errors, pass --force to run it anyway".to_owned());
+    }
+
+    let rx = subscribe_log();
+    for remaining in (1..=options.countdown).rev() {
+        println!("Starting in {remaining}...");
+        std::thread::sleep(Duration::from_secs(1));
+    }
+
+    let worker = std::thread::spawn(move || {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut backend = EnigoBackend::new(options.profile.char_delay);
+        commands(
+            &cmds,
+            &options.profile,
+            options.delay,
+            &catalog,
+            &Mutex::new(None),
+            &mut backend,
+            log,
+            &egui::Context::default(),
+        );
+    });
+    while !worker.is_finished() {
+        if let Ok(line) = rx.recv_timeout(Duration::from_millis(100)) {
+            println!("{line}");
+        }
+    }
+    for line in rx.try_iter() {
+        println!("{line}");
+    }
+    Ok(0)
+}
+
+// the paths given, with directories expanded to the morphs in them
+fn expand(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            files.extend(morph_files(path));
+        } else {
+            files.push(path.clone());
+        }
+    }
+    files
+}
+
+fn validate_paths(options: Options) -> Result<i32, String> {
+    let catalog = Catalog::load(&Catalog::path())?;
+    let metadata = metadata();
+    let files = expand(&options.paths);
+
+    let (mut errors, mut warnings) = (0, 0);
+    for file in &files {
+        // one unreadable file shouldn't hide what's wrong with the rest
+        let text = match read(file) {
+            Ok(text) => text,
+            Err(error) => {
+                errors += 1;
+                println!("{}: error: {error}", file.display());
+                continue;
+            }
+        };
+        let script = parse(&text);
+        let profile = script.apply(&options.profile);
+        for diagnostic in lint(&script, &profile, &catalog, &metadata) {
+            let severity = match diagnostic.severity {
+                Severity::Warning => {
+                    warnings += 1;
+                    "warning"
+                }
+                Severity::Error => {
+                    errors += 1;
+                    "error"
+                }
+            };
+            println!(
+                "{}:{}: {severity}: {}",
+                file.display(),
+                diagnostic.line + 1,
+                diagnostic.message
+            );
+        }
+    }
+
+    println!(
+        "{} file(s), {errors} error(s), {warnings} warning(s)",
+        files.len()
+    );
+    Ok(if errors > 0 { 1 } else { 0 })
+}
+
+// with --check nothing is written, and the exit code says whether anything would change
+fn format_paths(options: Options) -> Result<i32, String> {
+    let catalog = Catalog::load(&Catalog::path())?;
+    let files = expand(&options.paths);
+    let mut changed = 0;
+    for file in &files {
+        if format_file(file, &catalog, options.check)? {
+            changed += 1;
+            println!("{}", file.display());
+        }
+    }
+
+    if options.check {
+        println!("{changed} of {} file(s) need formatting", files.len());
+    } else {
+        println!("Formatted {changed} of {} file(s)", files.len());
+    }
+    Ok(if options.check && changed > 0 { 1 } else { 0 })
+}
+
+fn assets(args: &[String]) -> Result<i32, String> {
+    if args.len() == 2 && args[0] == "import" {
+        return import(Path::new(&args[1]));
+    }
+
+    let catalog = Catalog::load(&Catalog::path())?;
+    let dir = Settings::load(&Settings::path())?.library_dir();
+    let metadata = metadata();
+    let index = AssetIndex::build(&morph_files(&dir), &catalog);
+    let name = |id: u64| {
+        metadata
+            .get(id)
+            .map(|asset| asset.label())
+            .unwrap_or_default()
+    };
+    let show = |path: &Path| {
+        path.strip_prefix(&dir)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+
+    match args {
+        [] => {
+            for (id, uses) in index.ids() {
+                let morphs = index.morphs(id).len();
+                println!(
+                    "{id:>12}  {:<12} {morphs} morph(s)  {}",
+                    uses[0].command,
+                    name(id)
+                );
+            }
+            let repeated = index.repeated();
+            for (id, path) in &repeated {
+                println!("warning: {} gives {id} more than once", show(path));
+            }
+            println!(
+                "{} asset ID(s), {} shared by several morphs, {} repeated within a morph",
+                index.len(),
+                index.shared().len(),
+                repeated.len()
+            );
+            Ok(0)
+        }
+        [command, id] if command == "where" => {
+            let id = id.parse().map_err(|_| format!("{id} isn't an asset ID"))?;
+            let uses = index.where_used(id);
+            for u in uses {
+                println!("{}:{}: {}", show(&u.path), u.line + 1, u.command);
+            }
+            println!("{id} {} is used {} time(s)", name(id), uses.len());
+            Ok(if uses.is_empty() { 1 } else { 0 })
+        }
+        [command, morph] if command == "morph" => {
+            let path = match library::find(&dir, morph) {
+                Some(path) => path,
+                None => PathBuf::from(morph),
+            };
+            if !path.is_file() {
+                return Err(format!("No morph named {morph}"));
+            }
+            for (line, command, id) in asset_ids(&read(&path)?, &catalog) {
+                println!("{:>4}  {command:<12} {id}  {}", line + 1, name(id));
+            }
+            Ok(0)
+        }
+        [command, file] if command == "unused" => {
+            let text = read(Path::new(file))?;
+            let ids: Vec<u64> = text
+                .split(|c: char| !c.is_ascii_digit())
+                .filter_map(|word| word.parse().ok())
+                .collect();
+            let unused = index.unused(&ids);
+            for id in &unused {
+                println!("{id}");
+            }
+            println!(
+                "{} of {} ID(s) aren't used by any morph",
+                unused.len(),
+                ids.len()
+            );
+            Ok(0)
+        }
+        _ => Err(
+            "assets takes where <id>, morph <name|file>, unused <file> or import <file>".to_owned(),
+        ),
+    }
+}
+
+fn preview_file(options: Options) -> Result<i32, String> {
+    let cmds = read(one_file(&options.paths)?)?;
+    let catalog = Catalog::load(&Catalog::path())?;
+    let recording = preview(&cmds, &options.profile, options.delay, &catalog, None);
+
+    if options.json {
+        let json = serde_json::json!({
+            "total_ms": recording.total_ms(),
+            "steps": recording.steps,
+        });
+        println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        return Ok(0);
+    }
+
+    let metadata = metadata();
+    let mut elapsed = 0;
+    for step in &recording.steps {
+        println!("{elapsed:>6} ms  {}", step.describe());
+        if let Step::Paste(text) | Step::Type(text, _) = step {
+            // only batched submissions hold more than one command
+            let commands: Vec<&str> = if options.profile.batch {
+                text.split(options.profile.separator.as_str()).collect()
+            } else {
+                vec![text]
+            };
+            for name in commands
+                .into_iter()
+                .flat_map(|command| metadata.describe(command, &catalog))
+            {
+                println!("{:>12}{name}", "");
+            }
+        }
+        elapsed += step.duration();
+    }
+    println!("about {:.1} s", recording.total_ms() as f64 / 1000.0);
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_words_are_errors_and_paths_open_the_window() {
+        assert_eq!(main(&["rnu".to_owned()]), Some(2));
+        let path = std::env::temp_dir().join("auto_morph_cli_test.txt");
+        fs::write(&path, "hat me 1\n").unwrap();
+        assert_eq!(main(&[path.display().to_string()]), None);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn run_takes_one_file() {
+        let paths = [PathBuf::from("a.txt"), PathBuf::from("b.txt")];
+        assert!(one_file(&paths).is_err());
+        assert_eq!(one_file(&paths[..1]), Ok(Path::new("a.txt")));
+    }
+}
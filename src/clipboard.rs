@@ -0,0 +1,199 @@
+use arboard::{Clipboard, ImageData};
+
+// the clipboard calls the guard makes, so its decisions can be tested without a real one
+pub trait Board {
+    fn text(&mut self) -> Option<String>;
+    fn image(&mut self) -> Option<ImageData<'static>>;
+    fn set_text(&mut self, text: &str) -> bool;
+    fn set_image(&mut self, image: ImageData<'static>) -> bool;
+}
+
+impl Board for Clipboard {
+    fn text(&mut self) -> Option<String> {
+        self.get_text().ok()
+    }
+
+    fn image(&mut self) -> Option<ImageData<'static>> {
+        self.get_image().ok().map(|image| image.to_owned_img())
+    }
+
+    fn set_text(&mut self, text: &str) -> bool {
+        Clipboard::set_text(self, text).is_ok()
+    }
+
+    fn set_image(&mut self, image: ImageData<'static>) -> bool {
+        Clipboard::set_image(self, image).is_ok()
+    }
+}
+
+enum Snapshot {
+    Text(String),
+    Image(ImageData<'static>),
+    // empty, or only formats we can't read (HTML, files), which putting anything back would lose
+    Unreadable,
+}
+
+impl Snapshot {
+    fn take(clipboard: &mut impl Board) -> Self {
+        // images usually carry a text fallback too, so check them first
+        if let Some(image) = clipboard.image() {
+            return Snapshot::Image(image);
+        }
+        match clipboard.text() {
+            Some(text) => Snapshot::Text(text),
+            None => Snapshot::Unreadable,
+        }
+    }
+}
+
+pub struct ClipboardGuard<B: Board = Clipboard> {
+    clipboard: B,
+    original: Snapshot,
+    last_set: Option<String>,
+    touched: bool,
+    user_changed: bool,
+}
+
+impl ClipboardGuard {
+    pub fn new() -> Option<Self> {
+        Some(ClipboardGuard::with(Clipboard::new().ok()?))
+    }
+}
+
+impl<B: Board> ClipboardGuard<B> {
+    fn with(mut clipboard: B) -> Self {
+        let original = Snapshot::take(&mut clipboard);
+        Self {
+            clipboard,
+            original,
+            last_set: None,
+            touched: false,
+            user_changed: false,
+        }
+    }
+
+    // returns false when the clipboard doesn't read back as `text`
+    pub fn set_verified(&mut self, text: &str) -> bool {
+        if self.changed_since_last_set() {
+            // the user copied something mid-run, keep that instead of the original
+            self.original = Snapshot::take(&mut self.clipboard);
+            self.user_changed = true;
+        }
+
+        self.last_set = None;
+        self.touched = true;
+        for _ in 0..3 {
+            if self.clipboard.set_text(text) && self.clipboard.text().as_deref() == Some(text) {
+                self.last_set = Some(text.to_owned());
+                return true;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        false
+    }
+
+    fn changed_since_last_set(&mut self) -> bool {
+        match &self.last_set {
+            Some(last) => self.clipboard.text().as_deref() != Some(last.as_str()),
+            None => false,
+        }
+    }
+
+    pub fn user_changed(&self) -> bool {
+        self.user_changed
+    }
+
+    // an original that couldn't be read is left alone rather than cleared
+    pub fn restore(&mut self) -> bool {
+        if !self.touched {
+            return false;
+        }
+        if self.changed_since_last_set() {
+            // the user's newest copy is already on the clipboard
+            self.user_changed = true;
+            return false;
+        }
+        match std::mem::replace(&mut self.original, Snapshot::Unreadable) {
+            Snapshot::Text(text) => self.clipboard.set_text(&text),
+            Snapshot::Image(image) => self.clipboard.set_image(image),
+            Snapshot::Unreadable => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Fake {
+        text: Option<String>,
+    }
+
+    impl Board for Fake {
+        fn text(&mut self) -> Option<String> {
+            self.text.clone()
+        }
+
+        fn image(&mut self) -> Option<ImageData<'static>> {
+            None
+        }
+
+        fn set_text(&mut self, text: &str) -> bool {
+            self.text = Some(text.to_owned());
+            true
+        }
+
+        fn set_image(&mut self, _image: ImageData<'static>) -> bool {
+            false
+        }
+    }
+
+    fn holding(text: Option<&str>) -> ClipboardGuard<Fake> {
+        ClipboardGuard::with(Fake {
+            text: text.map(str::to_owned),
+        })
+    }
+
+    #[test]
+    fn the_original_text_comes_back() {
+        let mut guard = holding(Some("mine"));
+        assert!(guard.set_verified("hat me 1"));
+        assert!(guard.restore());
+        assert_eq!(guard.clipboard.text.as_deref(), Some("mine"));
+        assert!(!guard.user_changed());
+    }
+
+    #[test]
+    fn an_unreadable_original_is_left_alone() {
+        let mut guard = holding(None);
+        assert!(guard.set_verified("hat me 1"));
+        assert!(!guard.restore());
+        assert_eq!(guard.clipboard.text.as_deref(), Some("hat me 1"));
+    }
+
+    #[test]
+    fn a_copy_made_mid_run_is_kept() {
+        let mut guard = holding(Some("mine"));
+        guard.set_verified("hat me 1");
+        guard.clipboard.text = Some("copied".to_owned());
+        guard.set_verified("hat me 2");
+        assert!(guard.restore());
+        assert_eq!(guard.clipboard.text.as_deref(), Some("copied"));
+        assert!(guard.user_changed());
+
+        // copied after the last paste, it's already where it belongs
+        let mut guard = holding(Some("mine"));
+        guard.set_verified("hat me 1");
+        guard.clipboard.text = Some("newest".to_owned());
+        assert!(!guard.restore());
+        assert_eq!(guard.clipboard.text.as_deref(), Some("newest"));
+        assert!(guard.user_changed());
+    }
+
+    #[test]
+    fn nothing_is_restored_when_nothing_was_pasted() {
+        let mut guard = holding(Some("mine"));
+        assert!(!guard.restore());
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{
+    catalog::{ArgKind, Catalog},
+    parser::DIRECTIVES,
+};
+use std::{fs, ops::Range, path::PathBuf};
+
+const TARGETS: &[&str] = &["me", "others", "all", "random"];
+const MODES: &[&str] = &["command_bar", "chat"];
+
+#[derive(Clone, Debug)]
+pub struct Suggestion {
+    pub label: String,
+    pub insert: String,
+    pub detail: String,
+}
+
+// what could replace the word ending at `cursor` (a byte offset into `text`), `explicit` when
+// asked for with Ctrl+Space rather than by typing. `recent` are player names from the library
+pub fn suggestions(
+    text: &str,
+    cursor: usize,
+    catalog: &Catalog,
+    recent: &[String],
+    explicit: bool,
+) -> (Range<usize>, Vec<Suggestion>) {
+    let line_start = text[..cursor].rfind('\n').map_or(0, |i| i + 1);
+    let before = &text[line_start..cursor];
+    let word_start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+    let mut range = line_start + word_start..cursor;
+    let word = &before[word_start..];
+    let previous: Vec<&str> = before[..word_start].split_whitespace().collect();
+
+    let mut suggestions = Vec::new();
+    if let Some(rest) = before.trim_start().strip_prefix('#') {
+        // `#mo` then `#mode ch`
+        match previous.as_slice() {
+            [] => {
+                range.start += 1;
+                for directive in DIRECTIVES.iter().filter(|d| starts_with(d, rest)) {
+                    suggestions.push(Suggestion {
+                        label: directive.to_string(),
+                        insert: format!("{directive} "),
+                        detail: "directive".to_owned(),
+                    });
+                }
+            }
+            [first] if first.eq_ignore_ascii_case("#mode") => {
+                for mode in MODES.iter().filter(|m| starts_with(m, word)) {
+                    suggestions.push(Suggestion {
+                        label: mode.to_string(),
+                        insert: mode.to_string(),
+                        detail: "mode".to_owned(),
+                    });
+                }
+            }
+            _ => {}
+        }
+        return (range, suggestions);
+    }
+
+    match previous.split_first() {
+        None => {
+            let prefixed = word.starts_with(':');
+            if prefixed {
+                range.start += 1;
+            }
+            let word = word.trim_start_matches(':');
+            // a fresh line only lists everything on request
+            if word.is_empty() && !explicit {
+                return (range, suggestions);
+            }
+
+            for spec in &catalog.commands {
+                let alias = spec.aliases.iter().find(|alias| starts_with(alias, word));
+                if starts_with(&spec.name, word) || alias.is_some() {
+                    suggestions.push(Suggestion {
+                        label: spec.name.clone(),
+                        insert: format!("{} ", spec.name),
+                        detail: spec.usage(),
+                    });
+                }
+            }
+            // snippets are whole lines, so they're only offered at the start of one
+            if word_start == 0 && !prefixed {
+                for snippet in catalog
+                    .snippets
+                    .iter()
+                    .filter(|s| starts_with(&s.name, word))
+                {
+                    suggestions.push(Suggestion {
+                        label: format!("{} (snippet)", snippet.name),
+                        insert: snippet.body.clone(),
+                        detail: snippet.description.clone(),
+                    });
+                }
+            }
+        }
+        Some((name, args)) => {
+            let name = name.trim_start_matches(':');
+            let kind = catalog.find(name).and_then(|spec| spec.arg(args.len()));
+            if kind == Some(ArgKind::Player) {
+                for target in targets(text, catalog, recent) {
+                    if starts_with(&target, word) && !target.eq_ignore_ascii_case(word) {
+                        suggestions.push(Suggestion {
+                            insert: format!("{target} "),
+                            detail: "player".to_owned(),
+                            label: target,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    (range, suggestions)
+}
+
+// the usual targets, then names used in the morph, then ones from the library
+fn targets(text: &str, catalog: &Catalog, recent: &[String]) -> Vec<String> {
+    let mut targets: Vec<String> = TARGETS.iter().map(|t| t.to_string()).collect();
+    let used = names(text, catalog);
+    for target in used.iter().chain(recent) {
+        if !targets.iter().any(|t| t.eq_ignore_ascii_case(target)) {
+            targets.push(target.clone());
+        }
+    }
+    targets
+}
+
+// words in player argument positions, in the order they first show up
+fn names(text: &str, catalog: &Catalog) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for line in text
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+    {
+        let mut words = line.split_whitespace();
+        let Some(spec) = words
+            .next()
+            .and_then(|name| catalog.find(name.trim_start_matches(':')))
+        else {
+            continue;
+        };
+        for (index, word) in words.enumerate() {
+            let new = !names.iter().any(|name| name.eq_ignore_ascii_case(word));
+            if spec.arg(index) == Some(ArgKind::Player) && new {
+                names.push(word.to_owned());
+            }
+        }
+    }
+    names
+}
+
+// player names used across the library's morphs, for completion in any of them
+pub fn library_names(files: &[PathBuf], catalog: &Catalog) -> Vec<String> {
+    let mut all: Vec<String> = Vec::new();
+    for text in files
+        .iter()
+        .filter_map(|file| fs::read_to_string(file).ok())
+    {
+        for name in names(&text, catalog) {
+            if !all.iter().any(|known| known.eq_ignore_ascii_case(&name)) {
+                all.push(name);
+            }
+        }
+    }
+    all
+}
+
+fn starts_with(candidate: &str, prefix: &str) -> bool {
+    candidate
+        .get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(text: &str, recent: &[String]) -> Vec<String> {
+        let catalog = Catalog::bundled();
+        let (_, items) = suggestions(text, text.len(), &catalog, recent, false);
+        items.into_iter().map(|item| item.label).collect()
+    }
+
+    #[test]
+    fn targets_include_names_from_the_morph_and_the_library() {
+        let recent = vec!["Builderman".to_owned()];
+        let found = labels("hat Telamon 1\nhat ", &recent);
+        assert_eq!(found[..4], ["me", "others", "all", "random"]);
+        assert!(found.contains(&"Telamon".to_owned()));
+        assert!(found.contains(&"Builderman".to_owned()));
+        assert_eq!(labels("hat Bu", &recent), ["Builderman"]);
+    }
+
+    #[test]
+    fn only_player_arguments_count_as_names() {
+        let catalog = Catalog::bundled();
+        assert_eq!(names("hat me 12345\n# hat nope 1", &catalog), ["me"]);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{
+    catalog::Catalog,
+    dispatch::{Dispatcher, Job},
+    http,
+    ipc::{self, Control},
+    settings::{Binding, Settings, config_dir},
+    utils::{log_message, subscribe_log},
+};
+use eframe::egui;
+use serde_json::json;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex, mpsc},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(target_os = "macos")]
+use crate::macos::listen_bindings;
+#[cfg(target_os = "windows")]
+use crate::windows::listen_bindings;
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn listen_bindings(
+    _bindings: &[Binding],
+    _tx: mpsc::Sender<Job>,
+    _log: Arc<Mutex<Vec<String>>>,
+    _ctx: egui::Context,
+) -> Result<(), String> {
+    Err("Global hotkeys aren't supported on this platform".to_owned())
+}
+
+// runs the hotkey listener and worker without a window until stopped
+pub fn run(settings_path: &Path) -> Result<i32, String> {
+    // two listeners would both fire on the same hotkey
+    let listener = ipc::claim()
+        .map_err(|e| format!("Couldn't open the control socket: {e}"))?
+        .ok_or("auto_morph is already running")?;
+    let settings = Settings::load(settings_path)?;
+    if settings.bindings.is_empty() {
+        return Err(format!("No bindings in {}", settings_path.display()));
+    }
+    let profile = settings
+        .find_profile(&settings.profile)
+        .ok_or(format!("Unknown profile {}", settings.profile))?;
+
+    let log_path = settings
+        .log_file
+        .clone()
+        .unwrap_or_else(|| config_dir().join("daemon.log"));
+    if let Some(parent) = log_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut log_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .map_err(|e| format!("Couldn't open {}: {e}", log_path.display()))?;
+    let log_rx = subscribe_log();
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let ctx = egui::Context::default();
+    let catalog = Catalog::load(&Catalog::path()).unwrap_or_else(|error| {
+        log_message(&log, &error, &ctx);
+        Catalog::bundled()
+    });
+    let profile = Arc::new(Mutex::new(profile));
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let (job_tx, job_rx) = mpsc::channel();
+    let running = Dispatcher {
+        cmds: Arc::new(Mutex::new(String::new())),
+        delay: Arc::new(Mutex::new(settings.delay.to_string())),
+        profile: Arc::clone(&profile),
+        catalog: catalog.clone(),
+        applied: Arc::default(),
+        practice: None,
+        log: Arc::clone(&log),
+        ctx: ctx.clone(),
+    }
+    .spawn(job_rx);
+    let control = Control {
+        jobs: job_tx.clone(),
+        library_dir: settings.library_dir(),
+        profile,
+        catalog,
+        log: Arc::clone(&log),
+        ctx: ctx.clone(),
+        forwarded: None,
+        stop: Some(stop_tx.clone()),
+    };
+    control.clone().serve(listener);
+    if let Some(http_settings) = &settings.http {
+        let addr = http::serve(control, http_settings)?;
+        log_message(&log, &format!("HTTP API listening on http://{addr}"), &ctx);
+    }
+    listen_bindings(&settings.bindings, job_tx, Arc::clone(&log), ctx.clone())?;
+
+    ctrlc::set_handler(move || {
+        let _ = stop_tx.send(());
+    })
+    .map_err(|e| format!("Couldn't install the signal handler: {e}"))?;
+
+    log_message(
+        &log,
+        &format!(
+            "Daemon started with {} binding(s), logging to {}.",
+            settings.bindings.len(),
+            log_path.display()
+        ),
+        &ctx,
+    );
+
+    loop {
+        write_log(&mut log_file, &log_rx);
+        if stop_rx.recv_timeout(Duration::from_millis(250)).is_ok() {
+            break;
+        }
+    }
+
+    log_message(&log, "Stop requested, waiting for the current run.", &ctx);
+    // the lock also keeps a hotkey that lands now from starting another run
+    if let Some(worker) = running.lock().unwrap().take() {
+        let _ = worker.join();
+    }
+    log_message(&log, "Daemon stopped.", &ctx);
+    write_log(&mut log_file, &log_rx);
+    Ok(0)
+}
+
+// the daemon logs the stop when it picks the request up, a window refuses it
+pub fn request_stop() -> Result<i32, String> {
+    let Some(response) = ipc::send(&json!({ "cmd": "stop" })) else {
+        return Err("auto_morph isn't running".to_owned());
+    };
+    if response["ok"] != true {
+        return Err(response["error"]
+            .as_str()
+            .unwrap_or("The running instance refused to stop")
+            .to_owned());
+    }
+    Ok(0)
+}
+
+fn write_log(file: &mut fs::File, rx: &mpsc::Receiver<String>) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    for line in rx.try_iter() {
+        let _ = writeln!(file, "[{secs}] {line}");
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{
+    assets::Metadata,
+    backend::{Backend, EnigoBackend},
+    catalog::Catalog,
+    lint::{Diagnostic, has_errors, lint},
+    parser::{Script, parse},
+    practice::{Practice, PracticeBackend},
+    profile::Profile,
+    reset::Applied,
+    utils::{CANCEL, commands, log_message},
+};
+use eframe::egui;
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread::JoinHandle,
+};
+
+pub static IS_SIMULATING: AtomicBool = AtomicBool::new(false);
+
+// tests that set IS_SIMULATING or depend on it being clear take turns
+#[cfg(test)]
+pub static SIMULATING_TESTS: Mutex<()> = Mutex::new(());
+
+// the thread of the latest run, held so shutdown can wait for it to finish
+pub type Running = Arc<Mutex<Option<JoinHandle<()>>>>;
+
+pub enum Job {
+    // whatever was last armed with "Set Morph", which already asked about errors
+    Armed,
+    // a morph with lint errors only runs when forced, nobody is there to confirm it
+    File { path: PathBuf, force: bool },
+}
+
+// Err with every diagnostic when the morph has lint errors
+pub fn check(cmds: &str, profile: &Profile, catalog: &Catalog) -> Result<(), Vec<Diagnostic>> {
+    let script = parse(cmds);
+    // names and types only add warnings, a store that won't load doesn't block anything
+    let metadata = Metadata::load(&Metadata::path()).unwrap_or_default();
+    let diagnostics = lint(&script, &script.apply(profile), catalog, &metadata);
+    if has_errors(&diagnostics) {
+        return Err(diagnostics);
+    }
+    Ok(())
+}
+
+pub struct Dispatcher {
+    pub cmds: Arc<Mutex<String>>,
+    pub delay: Arc<Mutex<String>>,
+    pub profile: Arc<Mutex<Profile>>,
+    pub catalog: Catalog,
+    pub applied: Applied,
+    pub practice: Option<Arc<Mutex<Practice>>>,
+    pub log: Arc<Mutex<Vec<String>>>,
+    pub ctx: egui::Context,
+}
+
+impl Dispatcher {
+    pub fn spawn(self, jobs: mpsc::Receiver<Job>) -> Running {
+        let running: Running = Arc::default();
+        let current = Arc::clone(&running);
+        std::thread::spawn(move || {
+            for job in jobs {
+                // held until the run's handle is stored, so a shutdown can't slip in between
+                let mut current = current.lock().unwrap();
+                if IS_SIMULATING.load(Ordering::SeqCst) {
+                    log_message(
+                        &self.log,
+                        "Simulation already in progress, ignoring hotkey.",
+                        &self.ctx,
+                    );
+                    continue;
+                }
+                let profile = self.profile.lock().unwrap().clone();
+                let cmds = match job {
+                    Job::Armed => self.cmds.lock().unwrap().clone(),
+                    Job::File { path, force } => match fs::read_to_string(&path) {
+                        Ok(cmds) => match check(&cmds, &profile, &self.catalog) {
+                            Err(diagnostics) if !force => {
+                                for diagnostic in &diagnostics {
+                                    log_message(&self.log, &diagnostic.to_string(), &self.ctx);
+                                }
+                                let message =
+                                    format!("{} has errors, not running it.", path.display());
+                                log_message(&self.log, &message, &self.ctx);
+                                continue;
+                            }
+                            _ => cmds,
+                        },
+                        Err(error) => {
+                            log_message(
+                                &self.log,
+                                &format!("Couldn't read {}: {error}", path.display()),
+                                &self.ctx,
+                            );
+                            continue;
+                        }
+                    },
+                };
+                let delay = self.delay.lock().unwrap().parse::<u64>().unwrap_or(40);
+                let catalog = self.catalog.clone();
+                let thread_log = Arc::clone(&self.log);
+                let thread_ctx = self.ctx.clone();
+                log_message(
+                    &self.log,
+                    "Hotkey signal received, spawning a new simulation thread.",
+                    &self.ctx,
+                );
+                let practice = self
+                    .practice
+                    .as_ref()
+                    .filter(|practice| {
+                        let practice = practice.lock().unwrap();
+                        practice.open && practice.capture_runs
+                    })
+                    .cloned();
+                // practice runs don't reach the game, they get a copy to leave the real one alone
+                let applied = match practice {
+                    Some(_) => Arc::new(Mutex::new(self.applied.lock().unwrap().clone())),
+                    None => Arc::clone(&self.applied),
+                };
+                // set before the thread starts, a second hotkey right after this one is ignored
+                IS_SIMULATING.store(true, Ordering::SeqCst);
+                *current = Some(std::thread::spawn(move || {
+                    let mut backend: Box<dyn Backend> = match practice {
+                        Some(practice) => {
+                            Box::new(PracticeBackend::new(practice, thread_ctx.clone()))
+                        }
+                        None => Box::new(EnigoBackend::new(profile.char_delay)),
+                    };
+                    morph(
+                        &cmds,
+                        &profile,
+                        delay,
+                        &catalog,
+                        &applied,
+                        backend.as_mut(),
+                        thread_log,
+                        &thread_ctx,
+                    );
+                }));
+            }
+        });
+        running
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn morph(
+    cmds: &str,
+    profile: &Profile,
+    delay: u64,
+    catalog: &Catalog,
+    applied: &Mutex<Option<Script>>,
+    backend: &mut dyn Backend,
+    log: Arc<Mutex<Vec<String>>>,
+    ctx: &egui::Context,
+) {
+    if cmds.is_empty() {
+        log_message(&log, "Morph commands are empty, skipping.", ctx);
+        IS_SIMULATING.store(false, Ordering::SeqCst);
+        return;
+    }
+    log_message(&log, "Starting morph process...", ctx);
+    CANCEL.store(false, Ordering::SeqCst);
+    IS_SIMULATING.store(true, Ordering::SeqCst);
+    commands(cmds, profile, delay, catalog, applied, backend, log, ctx);
+    IS_SIMULATING.store(false, Ordering::SeqCst);
+}
@@ -0,0 +1,116 @@
+use crate::{
+    catalog::{ArgKind, Catalog},
+    parser::{directive, parse},
+};
+use std::{fs, path::Path};
+
+// one style for every morph: no `:` prefix (the profile adds it back in chat mode), single
+// spaces, catalog casing for command names, `\n` endings and at most one blank line in a row
+pub fn normalize(text: &str, catalog: &Catalog) -> String {
+    let script = parse(text);
+    let run_start = script
+        .commands
+        .first()
+        .filter(|_| script.is_run)
+        .map(|command| command.line);
+
+    let mut lines: Vec<String> = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        // a run script is code from its `run` line on, it's kept as written
+        if run_start.is_some_and(|start| i >= start) {
+            lines.push(raw.trim_end().to_owned());
+            continue;
+        }
+
+        let line = raw.trim();
+        let formatted = match line.strip_prefix('#') {
+            Some(rest) => match directive(rest) {
+                Some(("mode", value)) => format!("#mode {}", value.to_lowercase()),
+                Some((name, "")) => format!("#{name}"),
+                Some((name, value)) => format!("#{name} {value}"),
+                None => line.to_owned(),
+            },
+            None => command(line, catalog),
+        };
+        if !formatted.is_empty() || lines.last().is_some_and(|last| !last.is_empty()) {
+            lines.push(formatted);
+        }
+    }
+
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    if lines.is_empty() {
+        return String::new();
+    }
+    lines.join("\n") + "\n"
+}
+
+fn command(line: &str, catalog: &Catalog) -> String {
+    // `::hat` too, the executor only strips one
+    let body = line.trim_start_matches(':').trim_start();
+    let (name, rest) = body.split_once(char::is_whitespace).unwrap_or((body, ""));
+    let Some(spec) = catalog.find(name) else {
+        return body.split_whitespace().collect::<Vec<_>>().join(" ");
+    };
+
+    // aliases stay aliases, only their casing changes
+    let mut formatted = std::iter::once(&spec.name)
+        .chain(&spec.aliases)
+        .find(|candidate| candidate.eq_ignore_ascii_case(name))
+        .cloned()
+        .unwrap_or_default();
+    let mut rest = rest.trim_start();
+    let mut index = 0;
+    while !rest.is_empty() {
+        formatted.push(' ');
+        // text runs to the end of the line, spacing inside it is the user's
+        if spec.arg(index) == Some(ArgKind::Text) {
+            formatted.push_str(rest);
+            break;
+        }
+        let (word, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        formatted.push_str(word);
+        rest = tail.trim_start();
+        index += 1;
+    }
+    formatted
+}
+
+// rewrites the file if formatting changes it, returning whether it did
+pub fn format_file(path: &Path, catalog: &Catalog, check: bool) -> Result<bool, String> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {e}", path.display()))?;
+    let formatted = normalize(&text, catalog);
+    if formatted == text {
+        return Ok(false);
+    }
+    if !check {
+        fs::write(path, formatted)
+            .map_err(|e| format!("Couldn't write {}: {e}", path.display()))?;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directives_without_a_value_have_no_trailing_space() {
+        let catalog = Catalog::bundled();
+        assert_eq!(
+            normalize("#RESET  \n#Mode Chat\n:HAT  me   1\n", &catalog),
+            "#reset\n#mode chat\nhat me 1\n"
+        );
+    }
+
+    #[test]
+    fn run_scripts_are_kept_as_written() {
+        let catalog = Catalog::bundled();
+        assert_eq!(
+            normalize("# setup\n:run print(\"a  b\")  \n  print(1)\n", &catalog),
+            "# setup\n:run print(\"a  b\")\n  print(1)\n"
+        );
+    }
+}
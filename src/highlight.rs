@@ -0,0 +1,64 @@
+use crate::{
+    catalog::Catalog,
+    parser::{TokenKind, parse, tokens},
+};
+use eframe::egui::{
+    self, Color32, FontId, Stroke,
+    text::{LayoutJob, TextFormat},
+};
+
+fn color(kind: TokenKind, visuals: &egui::Visuals) -> Color32 {
+    match kind {
+        TokenKind::Prefix => visuals.weak_text_color(),
+        TokenKind::Command => Color32::from_rgb(86, 156, 214),
+        TokenKind::Target => Color32::from_rgb(197, 134, 192),
+        TokenKind::AssetId => Color32::from_rgb(181, 206, 168),
+        TokenKind::Argument => visuals.text_color(),
+        TokenKind::Comment => Color32::from_rgb(106, 153, 85),
+        TokenKind::Directive => Color32::from_rgb(206, 145, 120),
+        TokenKind::Error => visuals.error_fg_color,
+    }
+}
+
+// layout for the morph editor, colored by the parser's tokens
+pub fn layout_job(
+    text: &str,
+    catalog: &Catalog,
+    font: FontId,
+    visuals: &egui::Visuals,
+) -> LayoutJob {
+    let plain = TextFormat::simple(font.clone(), visuals.text_color());
+    let format = |kind: TokenKind| TextFormat {
+        color: color(kind, visuals),
+        underline: match kind {
+            TokenKind::Error => Stroke::new(1.0, visuals.error_fg_color),
+            _ => Stroke::NONE,
+        },
+        ..plain.clone()
+    };
+
+    // everything after `run` is sent verbatim, so it isn't split into commands
+    let script = parse(text);
+    let run_start = script
+        .commands
+        .first()
+        .filter(|_| script.is_run)
+        .map(|command| command.line);
+
+    let mut job = LayoutJob::default();
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        let line_tokens = match run_start {
+            Some(start) if i > start => Vec::new(),
+            _ => tokens(line, catalog),
+        };
+
+        let mut at = 0;
+        for token in line_tokens {
+            job.append(&line[at..token.range.start], 0.0, plain.clone());
+            job.append(&line[token.range.clone()], 0.0, format(token.kind));
+            at = token.range.end;
+        }
+        job.append(&line[at..], 0.0, plain.clone());
+    }
+    job
+}
@@ -0,0 +1,387 @@
+use crate::{
+    ipc::{Control, Request, progress},
+    settings::HttpSettings,
+    utils::subscribe_log,
+};
+use serde_json::{Value, json};
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tungstenite::{Message, WebSocket, handshake::derive_accept_key, protocol::Role};
+
+// binds to loopback only and returns the address, so port 0 picks a free one
+pub fn serve(control: Control, settings: &HttpSettings) -> Result<SocketAddr, String> {
+    if settings.token.is_empty() {
+        return Err("The HTTP API needs a token in settings.json".to_owned());
+    }
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, settings.port))
+        .map_err(|e| format!("Couldn't listen on port {}: {e}", settings.port))?;
+    let addr = listener.local_addr().map_err(|e| e.to_string())?;
+
+    let control = Arc::new(control);
+    let token = settings.token.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let control = Arc::clone(&control);
+            let token = token.clone();
+            std::thread::spawn(move || handle(stream, &control, &token));
+        }
+    });
+    Ok(addr)
+}
+
+// the parts of a request the routes look at, bodies are never needed
+struct Incoming {
+    method: String,
+    path: String,
+    query: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Incoming {
+    fn read(stream: &mut TcpStream) -> Option<Self> {
+        // a client that never finishes its request shouldn't hold a thread forever
+        stream.set_read_timeout(Some(Duration::from_secs(5))).ok()?;
+        let mut head = Vec::new();
+        let mut buf = [0; 1024];
+        while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+            if head.len() > 16 * 1024 {
+                return None;
+            }
+            let read = stream.read(&mut buf).ok().filter(|read| *read > 0)?;
+            head.extend_from_slice(&buf[..read]);
+        }
+
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut request = httparse::Request::new(&mut headers);
+        request.parse(&head).ok()?;
+        let url = request.path?;
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        Some(Self {
+            method: request.method?.to_owned(),
+            path: path.to_owned(),
+            query: query.to_owned(),
+            headers: request
+                .headers
+                .iter()
+                .map(|h| {
+                    (
+                        h.name.to_owned(),
+                        String::from_utf8_lossy(h.value).into_owned(),
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn handle(mut stream: TcpStream, control: &Control, token: &str) {
+    let Some(request) = Incoming::read(&mut stream) else {
+        return;
+    };
+
+    let bearer = request
+        .header("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_owned);
+    // browsers can't set headers on a WebSocket, so the query works too
+    let queried = param(&request.query, "token");
+    if !bearer.or(queried).is_some_and(|given| same(&given, token)) {
+        respond(
+            &mut stream,
+            401,
+            json!({ "ok": false, "error": "Missing or wrong token" }),
+        );
+        return;
+    }
+
+    let found = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/events") => return events(stream, &request),
+        ("GET", "/status") => Some(control.handle(Request::Status)),
+        ("GET", "/morphs") => Some(control.handle(Request::List)),
+        ("POST", "/cancel") => Some(control.handle(Request::Cancel)),
+        ("POST", path) => path.strip_prefix("/run/").map(|name| {
+            let force = param(&request.query, "force").is_some_and(|v| v == "1" || v == "true");
+            control.handle(Request::Run {
+                name: decode(name),
+                force,
+            })
+        }),
+        _ => None,
+    };
+
+    match found {
+        Some(body) => {
+            let status = match (&body["ok"], body["error"].as_str()) {
+                (Value::Bool(true), _) => 200,
+                (_, Some("busy")) => 409,
+                _ if body.get("diagnostics").is_some() => 422,
+                _ => 404,
+            };
+            respond(&mut stream, status, body);
+        }
+        None => respond(
+            &mut stream,
+            404,
+            json!({ "ok": false, "error": "Not found" }),
+        ),
+    }
+}
+
+// compares every byte whatever the first mismatch, so response times don't give the token away
+fn same(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+// streams log lines and progress changes until the client closes or stops answering
+fn events(mut stream: TcpStream, request: &Incoming) {
+    let Some(key) = request.header("Sec-WebSocket-Key") else {
+        respond(
+            &mut stream,
+            400,
+            json!({ "ok": false, "error": "Expected a WebSocket" }),
+        );
+        return;
+    };
+    let accepted = write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        derive_accept_key(key.as_bytes())
+    );
+    // short reads let one thread answer the client and send events in turn
+    if accepted.is_err()
+        || stream
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .is_err()
+    {
+        return;
+    }
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+
+    // dropped on return, which takes this client off the log subscribers
+    let rx = subscribe_log();
+    let mut last = Value::Null;
+    let mut pinged = Instant::now();
+    loop {
+        // pongs and the close handshake are queued by read and sent on the next read or send
+        match socket.read() {
+            // sending anything after a close fails, so answer it before events pile up
+            Ok(Message::Close(_)) => {
+                let _ = socket.flush();
+                return;
+            }
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => return,
+        }
+
+        let mut events: Vec<Message> = rx
+            .try_iter()
+            .map(|line| Message::text(json!({ "event": "log", "line": line }).to_string()))
+            .collect();
+        let current = progress();
+        if current != last {
+            let event = json!({ "event": "progress", "progress": current });
+            events.push(Message::text(event.to_string()));
+            last = current;
+        }
+        // a client that vanished without closing is only noticed when a write fails
+        if pinged.elapsed() > Duration::from_secs(10) {
+            events.push(Message::Ping(Vec::new().into()));
+            pinged = Instant::now();
+        }
+
+        for event in events {
+            if socket.send(event).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: u16, body: Value) {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        409 => "Conflict",
+        422 => "Unprocessable Entity",
+        _ => "Not Found",
+    };
+    let body = body.to_string();
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+}
+
+fn param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+        .map(decode)
+}
+
+// percent-decoding for morph names and tokens in urls
+fn decode(text: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut chars = text.bytes();
+    while let Some(byte) = chars.next() {
+        let hex = match byte {
+            b'%' => chars.clone().take(2).map(char::from).collect::<String>(),
+            b'+' => {
+                bytes.push(b' ');
+                continue;
+            }
+            _ => {
+                bytes.push(byte);
+                continue;
+            }
+        };
+        match u8::from_str_radix(&hex, 16) {
+            Ok(value) if hex.len() == 2 => {
+                bytes.push(value);
+                chars.nth(1);
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dispatch::SIMULATING_TESTS, ipc::Fixture, utils::log_message};
+    use eframe::egui;
+    use std::sync::Mutex;
+
+    const TOKEN: &str = "secret";
+
+    // the fixture has to outlive the test's requests
+    fn start(test: &str) -> (SocketAddr, Fixture) {
+        let fixture = Fixture::new(
+            test,
+            &[("guard.txt", "hat me 1\n"), ("typo.txt", "hatt me 1\n")],
+        );
+        let settings = HttpSettings {
+            port: 0,
+            token: TOKEN.to_owned(),
+        };
+        let addr = serve(fixture.control.clone(), &settings).unwrap();
+        (addr, fixture)
+    }
+
+    fn request(addr: SocketAddr, method: &str, path: &str, token: Option<&str>) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let auth = token.map_or(String::new(), |token| {
+            format!("Authorization: Bearer {token}\r\n")
+        });
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nHost: localhost\r\n{auth}\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn requests_need_the_token() {
+        let (addr, _library) = start("http_token");
+        assert_eq!(request(addr, "GET", "/status", None).0, 401);
+        assert_eq!(request(addr, "GET", "/status", Some("secreT")).0, 401);
+        assert_eq!(request(addr, "GET", "/status", Some("secret2")).0, 401);
+        assert_eq!(request(addr, "GET", "/status?token=secret", None).0, 200);
+        assert!(same(TOKEN, "secret") && !same(TOKEN, "secre"));
+    }
+
+    #[test]
+    fn morphs_and_run() {
+        let _turn = SIMULATING_TESTS.lock().unwrap_or_else(|e| e.into_inner());
+        let (addr, _library) = start("http_run");
+        let (status, body) = request(addr, "GET", "/morphs", Some(TOKEN));
+        assert_eq!(status, 200);
+        assert_eq!(body["morphs"][0]["name"], "guard");
+        assert_eq!(body["morphs"][1]["name"], "typo");
+
+        assert_eq!(
+            request(addr, "POST", "/run/guard", Some(TOKEN)),
+            (200, json!({ "ok": true }))
+        );
+        let (status, body) = request(addr, "POST", "/run/nobody", Some(TOKEN));
+        assert_eq!(status, 404);
+        assert_eq!(body["ok"], false);
+
+        let (status, body) = request(addr, "POST", "/run/typo", Some(TOKEN));
+        assert_eq!(status, 422);
+        assert!(
+            body["diagnostics"]
+                .as_array()
+                .is_some_and(|d| !d.is_empty())
+        );
+        assert_eq!(
+            request(addr, "POST", "/run/typo?force=1", Some(TOKEN)).0,
+            200
+        );
+    }
+
+    #[test]
+    fn events_stream_log_lines() {
+        let (addr, _library) = start("http_events");
+        let stream = TcpStream::connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let url = format!("ws://{addr}/events?token={TOKEN}");
+        let (mut socket, _) = tungstenite::client(url, stream).unwrap();
+
+        // the server subscribes after the handshake, keep logging until a line comes through
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let ctx = egui::Context::default();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            assert!(Instant::now() < deadline, "no log event arrived");
+            log_message(&log, "http events test", &ctx);
+            let Message::Text(text) = socket.read().unwrap() else {
+                continue;
+            };
+            let event: Value = serde_json::from_str(&text).unwrap();
+            if event["event"] == "log" && event["line"] == "http events test" {
+                break;
+            }
+        }
+
+        // the server answers the close instead of leaving it hanging
+        socket.close(None).unwrap();
+        loop {
+            match socket.read() {
+                Ok(_) => {}
+                Err(tungstenite::Error::ConnectionClosed) => break,
+                Err(error) => panic!("{error}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{
+    catalog::{ArgKind, Catalog},
+    parser::parse,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+// one place an asset ID is given
+#[derive(Clone, Debug)]
+pub struct Use {
+    pub path: PathBuf,
+    pub line: usize,
+    pub command: String,
+}
+
+// the asset IDs in a morph as (line, command name, id), in order
+pub fn asset_ids(text: &str, catalog: &Catalog) -> Vec<(usize, String, u64)> {
+    let script = parse(text);
+    if script.is_run {
+        return Vec::new();
+    }
+
+    let mut ids = Vec::new();
+    for command in &script.commands {
+        let mut words = command.body().split_whitespace();
+        let Some(name) = words.next() else {
+            continue;
+        };
+        let spec = catalog.find(name);
+        for (index, word) in words.enumerate() {
+            // without a spec, any number after the target is taken for an ID
+            let is_id = match spec {
+                Some(spec) => spec.arg(index) == Some(ArgKind::AssetId),
+                None => index > 0,
+            };
+            if let Some(id) = word.parse().ok().filter(|_| is_id) {
+                let name = spec.map_or(name, |spec| spec.name.as_str());
+                ids.push((command.line, name.to_owned(), id));
+            }
+        }
+    }
+    ids
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AssetIndex {
+    uses: BTreeMap<u64, Vec<Use>>,
+}
+
+impl AssetIndex {
+    // files that can't be read are left out
+    pub fn build(files: &[PathBuf], catalog: &Catalog) -> Self {
+        let mut index = Self::default();
+        for path in files {
+            if let Ok(text) = fs::read_to_string(path) {
+                index.add(path, &text, catalog);
+            }
+        }
+        index
+    }
+
+    fn add(&mut self, path: &Path, text: &str, catalog: &Catalog) {
+        for (line, command, id) in asset_ids(text, catalog) {
+            self.uses.entry(id).or_default().push(Use {
+                path: path.to_owned(),
+                line,
+                command,
+            });
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.uses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.uses.is_empty()
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = (u64, &[Use])> {
+        self.uses.iter().map(|(id, uses)| (*id, uses.as_slice()))
+    }
+
+    pub fn where_used(&self, id: u64) -> &[Use] {
+        self.uses.get(&id).map_or(&[], |uses| uses.as_slice())
+    }
+
+    pub fn morphs(&self, id: u64) -> Vec<&Path> {
+        let mut morphs: Vec<&Path> = self
+            .where_used(id)
+            .iter()
+            .map(|u| u.path.as_path())
+            .collect();
+        morphs.sort();
+        morphs.dedup();
+        morphs
+    }
+
+    // IDs more than one morph uses, which a cleanup has to fix everywhere
+    pub fn shared(&self) -> Vec<u64> {
+        self.uses
+            .keys()
+            .copied()
+            .filter(|id| self.morphs(*id).len() > 1)
+            .collect()
+    }
+
+    // IDs a single morph gives more than once, usually a copy-paste slip
+    pub fn repeated(&self) -> Vec<(u64, &Path)> {
+        let mut repeated = Vec::new();
+        for (id, uses) in &self.uses {
+            for path in self.morphs(*id) {
+                if uses.iter().filter(|u| u.path == path).count() > 1 {
+                    repeated.push((*id, path));
+                }
+            }
+        }
+        repeated
+    }
+
+    // the IDs out of `ids` that no morph uses
+    pub fn unused(&self, ids: &[u64]) -> Vec<u64> {
+        ids.iter()
+            .copied()
+            .filter(|id| !self.uses.contains_key(id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(morphs: &[(&str, &str)]) -> AssetIndex {
+        let catalog = Catalog::bundled();
+        let mut index = AssetIndex::default();
+        for (path, text) in morphs {
+            index.add(Path::new(path), text, &catalog);
+        }
+        index
+    }
+
+    #[test]
+    fn ids_come_from_asset_id_arguments() {
+        let catalog = Catalog::bundled();
+        assert_eq!(
+            asset_ids(":hat me 1 2\nsize me 3\n# hat me 4\n", &catalog),
+            [(0, "hat".to_owned(), 1), (0, "hat".to_owned(), 2)]
+        );
+        assert_eq!(
+            asset_ids("permhats me 5\n", &catalog),
+            [(0, "permhat".to_owned(), 5)]
+        );
+        assert!(asset_ids("run\nhat me 1\n", &catalog).is_empty());
+    }
+
+    #[test]
+    fn unknown_commands_take_numbers_after_the_target() {
+        let catalog = Catalog::bundled();
+        assert_eq!(
+            asset_ids("gear 42 7 x\n", &catalog),
+            [(0, "gear".to_owned(), 7)]
+        );
+    }
+
+    #[test]
+    fn shared_repeated_and_unused() {
+        let index = index(&[
+            ("a.txt", "hat me 1\nshirt me 2\nhat me 1\n"),
+            ("b.txt", "hat me 1\npants me 3\n"),
+            ("c.txt", "hat me 1\n"),
+        ]);
+        assert_eq!(index.len(), 3);
+        assert_eq!(
+            index.morphs(1),
+            [Path::new("a.txt"), Path::new("b.txt"), Path::new("c.txt")]
+        );
+        assert_eq!(index.shared(), [1]);
+        assert_eq!(index.repeated(), [(1, Path::new("a.txt"))]);
+        assert_eq!(index.where_used(2)[0].line, 1);
+        assert_eq!(index.unused(&[1, 2, 9]), [9]);
+    }
+
+    #[test]
+    fn morphs_are_listed_once_however_the_uses_interleave() {
+        let index = index(&[("a.txt", "hat me 1\n"), ("b.txt", "hat me 1\n")]);
+        let mut uses = index.clone();
+        uses.uses.get_mut(&1).unwrap().push(Use {
+            path: PathBuf::from("a.txt"),
+            line: 5,
+            command: "hat".to_owned(),
+        });
+        assert_eq!(uses.morphs(1).len(), 2);
+        assert_eq!(uses.repeated(), [(1, Path::new("a.txt"))]);
+    }
+}
@@ -0,0 +1,360 @@
+use crate::{
+    catalog::Catalog,
+    dispatch::{IS_SIMULATING, Job, check},
+    library,
+    profile::Profile,
+    utils::{CANCEL, PROGRESS, log_message, subscribe_log},
+};
+use eframe::egui;
+use interprocess::local_socket::{
+    GenericNamespaced, Listener, ListenerOptions, Name, Stream, prelude::*,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex, atomic::Ordering, mpsc},
+};
+
+// a named pipe on Windows, a Unix domain socket elsewhere
+const SOCKET_NAME: &str = "auto_morph.sock";
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    List,
+    // `force` runs a morph with lint errors anyway
+    Run {
+        name: String,
+        #[serde(default)]
+        force: bool,
+    },
+    Cancel,
+    Status,
+    Subscribe,
+    // `auto_morph stop`, only a daemon takes it
+    Stop,
+    // arguments from a second launch, handed to the running window
+    Forward {
+        args: Vec<String>,
+    },
+}
+
+pub fn socket_name() -> io::Result<Name<'static>> {
+    SOCKET_NAME.to_ns_name::<GenericNamespaced>()
+}
+
+pub fn progress() -> Value {
+    match *PROGRESS.lock().unwrap() {
+        Some((line, total)) => json!({ "line": line, "total": total }),
+        None => Value::Null,
+    }
+}
+
+// binds the socket that makes this the running instance, None when another one already has it.
+// Binding first means two launches at once can't both get past the check
+pub fn claim() -> io::Result<Option<Listener>> {
+    claim_name(SOCKET_NAME)
+}
+
+fn claim_name(name: &str) -> io::Result<Option<Listener>> {
+    let bind = |overwrite| {
+        ListenerOptions::new()
+            .name(name.to_ns_name::<GenericNamespaced>()?)
+            .try_overwrite(overwrite)
+            .create_sync()
+    };
+    match bind(false) {
+        Ok(listener) => Ok(Some(listener)),
+        Err(e) if e.kind() == ErrorKind::AddrInUse => {
+            if Stream::connect(name.to_ns_name::<GenericNamespaced>()?).is_ok() {
+                return Ok(None);
+            }
+            // nothing answered, the socket was left behind by an instance that crashed
+            bind(true).map(Some)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// one request to a running instance, None when nothing is listening
+pub fn send(request: &Value) -> Option<Value> {
+    let stream = Stream::connect(socket_name().ok()?).ok()?;
+    let (recv, mut send) = stream.split();
+    writeln!(send, "{request}").ok()?;
+    let mut line = String::new();
+    BufReader::new(recv).read_line(&mut line).ok()?;
+    serde_json::from_str(&line).ok()
+}
+
+// paths are made absolute since the running instance has its own working directory
+pub fn forward(args: &[String]) -> Option<Value> {
+    let args: Vec<String> = args
+        .iter()
+        .map(|arg| match fs::canonicalize(arg) {
+            Ok(path) => path.display().to_string(),
+            Err(_) => arg.clone(),
+        })
+        .collect();
+    send(&json!({ "cmd": "forward", "args": args }))
+}
+
+// shared by every local control surface, feeds the hotkey job queue
+#[derive(Clone)]
+pub struct Control {
+    pub jobs: mpsc::Sender<Job>,
+    pub library_dir: PathBuf,
+    // what runs are linted with before they're queued
+    pub profile: Arc<Mutex<Profile>>,
+    pub catalog: Catalog,
+    pub log: Arc<Mutex<Vec<String>>>,
+    pub ctx: egui::Context,
+    // None when there's no window to hand forwarded arguments to
+    pub forwarded: Option<mpsc::Sender<Vec<String>>>,
+    // the daemon's shutdown, None for the window
+    pub stop: Option<mpsc::Sender<()>>,
+}
+
+impl Control {
+    pub fn handle(&self, request: Request) -> Value {
+        match request {
+            Request::List => {
+                let morphs: Vec<Value> = library::entries(&self.library_dir)
+                    .into_iter()
+                    .map(|entry| json!({ "name": entry.name, "path": entry.path }))
+                    .collect();
+                json!({ "ok": true, "morphs": morphs })
+            }
+            // the dispatcher would drop it, the caller should know rather than wait for nothing
+            Request::Run { .. } if IS_SIMULATING.load(Ordering::SeqCst) => {
+                json!({ "ok": false, "error": "busy" })
+            }
+            Request::Run { name, force } => match library::find(&self.library_dir, &name) {
+                Some(path) => {
+                    // a file that can't be read is reported by the dispatcher
+                    let cmds = fs::read_to_string(&path).unwrap_or_default();
+                    let profile = self.profile.lock().unwrap().clone();
+                    let checked = if force {
+                        Ok(())
+                    } else {
+                        check(&cmds, &profile, &self.catalog)
+                    };
+                    if let Err(diagnostics) = checked {
+                        let diagnostics: Vec<String> =
+                            diagnostics.iter().map(ToString::to_string).collect();
+                        return json!({
+                            "ok": false,
+                            "error": "The morph has errors, pass force to run it anyway",
+                            "diagnostics": diagnostics,
+                        });
+                    }
+                    log_message(&self.log, &format!("Run requested for {name}."), &self.ctx);
+                    let _ = self.jobs.send(Job::File { path, force });
+                    json!({ "ok": true })
+                }
+                None => json!({ "ok": false, "error": format!("No morph named {name}") }),
+            },
+            Request::Cancel => {
+                let running = IS_SIMULATING.load(Ordering::SeqCst);
+                if running {
+                    CANCEL.store(true, Ordering::SeqCst);
+                }
+                json!({ "ok": true, "cancelled": running })
+            }
+            Request::Status => json!({
+                "ok": true,
+                "running": IS_SIMULATING.load(Ordering::SeqCst),
+                "progress": progress(),
+                "library": self.library_dir,
+            }),
+            // streaming is up to the transport
+            Request::Subscribe => json!({ "ok": true }),
+            Request::Forward { args } => match &self.forwarded {
+                Some(tx) => {
+                    let _ = tx.send(args);
+                    self.ctx.request_repaint();
+                    json!({ "ok": true })
+                }
+                None => json!({ "ok": false, "error": "auto_morph is running as a daemon" }),
+            },
+            Request::Stop => match &self.stop {
+                Some(tx) => {
+                    let _ = tx.send(());
+                    json!({ "ok": true })
+                }
+                None => json!({
+                    "ok": false,
+                    "error": "auto_morph is running as a window, close it instead",
+                }),
+            },
+        }
+    }
+
+    // answers on the listener `claim` bound
+    pub fn serve(self, listener: Listener) {
+        let control = Arc::new(self);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let control = Arc::clone(&control);
+                std::thread::spawn(move || control.connection(stream));
+            }
+        });
+    }
+
+    fn connection(&self, stream: Stream) {
+        let (recv, mut send) = stream.split();
+        for line in BufReader::new(recv).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match serde_json::from_str::<Request>(&line) {
+                Ok(Request::Subscribe) => {
+                    let _ = writeln!(send, "{}", json!({ "ok": true }));
+                    for line in subscribe_log() {
+                        let event = json!({ "event": "log", "line": line });
+                        if writeln!(send, "{event}").is_err() {
+                            return;
+                        }
+                    }
+                    return;
+                }
+                Ok(request) => self.handle(request),
+                Err(error) => json!({ "ok": false, "error": error.to_string() }),
+            };
+            if writeln!(send, "{response}").is_err() {
+                break;
+            }
+        }
+    }
+}
+
+// a Control over its own library folder, removed when the test is done
+#[cfg(test)]
+pub struct Fixture {
+    pub control: Control,
+    // kept so queued runs are accepted, and to see what was queued
+    pub queued: mpsc::Receiver<Job>,
+}
+
+#[cfg(test)]
+impl Fixture {
+    pub fn new(test: &str, morphs: &[(&str, &str)]) -> Self {
+        let dir = std::env::temp_dir().join(format!("auto_morph_{}_{test}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, text) in morphs {
+            fs::write(dir.join(name), text).unwrap();
+        }
+        let (jobs, queued) = mpsc::channel();
+        let control = Control {
+            jobs,
+            library_dir: dir,
+            log: Arc::new(Mutex::new(Vec::new())),
+            ctx: egui::Context::default(),
+            profile: Arc::default(),
+            catalog: Catalog::bundled(),
+            forwarded: None,
+            stop: None,
+        };
+        Self { control, queued }
+    }
+}
+
+#[cfg(test)]
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.control.library_dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dispatch::SIMULATING_TESTS;
+
+    fn run(control: &Control, name: &str, force: bool) -> Value {
+        control.handle(Request::Run {
+            name: name.to_owned(),
+            force,
+        })
+    }
+
+    #[test]
+    fn run_is_refused_while_busy() {
+        let _turn = SIMULATING_TESTS.lock().unwrap_or_else(|e| e.into_inner());
+        let Fixture { control, queued } = &Fixture::new("ipc_busy", &[("hats.txt", "hat me 1\n")]);
+
+        IS_SIMULATING.store(true, Ordering::SeqCst);
+        let busy = run(control, "hats", false);
+        IS_SIMULATING.store(false, Ordering::SeqCst);
+        assert_eq!(busy, json!({ "ok": false, "error": "busy" }));
+        assert!(queued.try_recv().is_err());
+
+        assert_eq!(run(control, "hats", false), json!({ "ok": true }));
+        assert!(matches!(
+            queued.try_recv(),
+            Ok(Job::File { force: false, .. })
+        ));
+    }
+
+    #[test]
+    fn morphs_with_errors_need_force() {
+        let _turn = SIMULATING_TESTS.lock().unwrap_or_else(|e| e.into_inner());
+        let Fixture { control, queued } =
+            &Fixture::new("ipc_force", &[("typo.txt", "hatt me 1\n")]);
+
+        let refused = run(control, "typo", false);
+        assert_eq!(refused["ok"], false);
+        assert!(
+            refused["diagnostics"][0]
+                .as_str()
+                .unwrap()
+                .contains("line 1")
+        );
+        assert!(queued.try_recv().is_err());
+
+        assert_eq!(run(control, "typo", true), json!({ "ok": true }));
+        assert!(matches!(
+            queued.try_recv(),
+            Ok(Job::File { force: true, .. })
+        ));
+    }
+
+    #[test]
+    fn only_the_daemon_stops() {
+        let mut fixture = Fixture::new("ipc_stop", &[]);
+        let refused = fixture.control.handle(Request::Stop);
+        assert_eq!(refused["ok"], false);
+
+        let (stop, stopped) = mpsc::channel();
+        fixture.control.stop = Some(stop);
+        assert_eq!(fixture.control.handle(Request::Stop), json!({ "ok": true }));
+        assert!(stopped.try_recv().is_ok());
+    }
+
+    #[test]
+    fn only_one_launch_claims_the_socket() {
+        let name = format!("auto_morph_claim_{}.sock", std::process::id());
+        let name = name.as_str();
+        let launches: Vec<_> = (0..4)
+            .map(|_| {
+                let name = name.to_owned();
+                std::thread::spawn(move || claim_name(&name).unwrap())
+            })
+            .collect();
+        let claimed: Vec<Listener> = launches
+            .into_iter()
+            .filter_map(|launch| launch.join().unwrap())
+            .collect();
+        assert_eq!(claimed.len(), 1);
+        assert!(claim_name(name).unwrap().is_none());
+
+        drop(claimed);
+        assert!(claim_name(name).unwrap().is_some());
+    }
+}
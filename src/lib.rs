@@ -1,5 +1,29 @@
 pub mod app;
+pub mod assets;
+pub mod backend;
+pub mod builder;
+pub mod catalog;
+pub mod cli;
+pub mod clipboard;
+pub mod complete;
+pub mod daemon;
+pub mod dispatch;
+pub mod format;
+pub mod highlight;
+pub mod http;
+pub mod index;
+pub mod ipc;
+pub mod library;
+pub mod lint;
 #[cfg(target_os = "macos")]
 pub mod macos;
+pub mod merge;
+pub mod parser;
+pub mod practice;
+pub mod profile;
+pub mod replace;
+pub mod reset;
+pub mod settings;
 pub mod utils;
+pub mod watch;
 pub mod windows;
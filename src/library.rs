@@ -0,0 +1,141 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub fn is_morph_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("txt" | "morph")
+    )
+}
+
+// morph files under `dir`, sorted so listings are stable
+pub fn morph_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return files;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(morph_files(&path));
+        } else if is_morph_file(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+pub struct Entry {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+pub fn entries(dir: &Path) -> Vec<Entry> {
+    morph_files(dir)
+        .into_iter()
+        .map(|path| Entry {
+            name: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path,
+        })
+        .collect()
+}
+
+pub fn find(dir: &Path, name: &str) -> Option<PathBuf> {
+    entries(dir)
+        .into_iter()
+        .find(|entry| entry.name.eq_ignore_ascii_case(name))
+        .map(|entry| entry.path)
+}
+
+// where files opened or dropped together go
+#[derive(Debug, PartialEq, Eq)]
+pub enum Route {
+    Nothing,
+    // a single file is opened in the editor
+    Editor(PathBuf),
+    // several are added to the library, a name given twice only once
+    Library(Vec<PathBuf>),
+}
+
+pub fn route(paths: &[PathBuf]) -> Route {
+    match paths {
+        [] => Route::Nothing,
+        [path] => Route::Editor(path.clone()),
+        paths => {
+            let mut unique: Vec<PathBuf> = Vec::new();
+            for path in paths {
+                if !unique
+                    .iter()
+                    .any(|seen| seen.file_name() == path.file_name())
+                {
+                    unique.push(path.clone());
+                }
+            }
+            Route::Library(unique)
+        }
+    }
+}
+
+// copies a morph into the library, refusing to replace one that's already there
+pub fn add(dir: &Path, path: &Path) -> Result<PathBuf, String> {
+    if !is_morph_file(path) {
+        return Err(format!("{} isn't a .txt or .morph file", path.display()));
+    }
+    let name = path.file_name().ok_or("Missing a file name")?;
+    let target = dir.join(name);
+    if target.exists() {
+        return Err(format!(
+            "{} is already in the library",
+            name.to_string_lossy()
+        ));
+    }
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    fs::copy(path, &target).map_err(|e| format!("Couldn't copy {}: {e}", path.display()))?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_file_is_opened_and_several_are_added() {
+        assert_eq!(route(&[]), Route::Nothing);
+        let one = PathBuf::from("a/guard.txt");
+        assert_eq!(route(std::slice::from_ref(&one)), Route::Editor(one));
+
+        let paths = ["a/guard.txt", "b/hats.txt", "c/guard.txt", "a/guard.txt"].map(PathBuf::from);
+        assert_eq!(
+            route(&paths),
+            Route::Library(vec![paths[0].clone(), paths[1].clone()])
+        );
+    }
+
+    #[test]
+    fn add_keeps_what_is_already_there() {
+        let root = std::env::temp_dir().join(format!("auto_morph_{}_library", std::process::id()));
+        let (outside, dir) = (root.join("outside"), root.join("morphs"));
+        fs::create_dir_all(&outside).unwrap();
+        let morph = outside.join("guard.txt");
+        fs::write(&morph, "hat me 1\n").unwrap();
+        fs::write(outside.join("notes.md"), "").unwrap();
+
+        assert_eq!(add(&dir, &morph), Ok(dir.join("guard.txt")));
+        fs::write(&morph, "hat me 2\n").unwrap();
+        assert!(add(&dir, &morph).is_err());
+        assert_eq!(
+            fs::read_to_string(dir.join("guard.txt")).unwrap(),
+            "hat me 1\n"
+        );
+        assert!(add(&dir, &outside.join("notes.md")).is_err());
+        assert_eq!(find(&dir, "GUARD"), Some(dir.join("guard.txt")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
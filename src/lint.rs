@@ -0,0 +1,336 @@
+use crate::{
+    assets::Metadata,
+    catalog::{ArgKind, Catalog, Spec},
+    parser::{Script, split},
+    profile::Profile,
+};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(line: usize, message: String) -> Self {
+        Self {
+            line,
+            severity: Severity::Error,
+            message,
+        }
+    }
+
+    fn warning(line: usize, message: String) -> Self {
+        Self {
+            line,
+            severity: Severity::Warning,
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{severity} on line {}: {}", self.line + 1, self.message)
+    }
+}
+
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+pub fn lint(
+    script: &Script,
+    profile: &Profile,
+    catalog: &Catalog,
+    metadata: &Metadata,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    // run scripts are code, none of the command rules apply
+    if script.is_run {
+        return diagnostics;
+    }
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut set: HashMap<(String, String), usize> = HashMap::new();
+    for command in &script.commands {
+        let words: Vec<&str> = command.body().split_whitespace().collect();
+        let Some((name, args)) = words.split_first() else {
+            diagnostics.push(Diagnostic::error(
+                command.line,
+                "Empty command after the `:`".to_owned(),
+            ));
+            continue;
+        };
+
+        let normalized = words.join(" ").to_lowercase();
+        let duplicate = match seen.get(&normalized) {
+            Some(first) => {
+                diagnostics.push(Diagnostic::warning(
+                    command.line,
+                    format!("Same command as line {}", first + 1),
+                ));
+                true
+            }
+            None => {
+                seen.insert(normalized, command.line);
+                false
+            }
+        };
+
+        match catalog.find(name) {
+            None => {
+                let hint = closest(name, catalog)
+                    .map(|close| format!(", did you mean {close}?"))
+                    .unwrap_or_default();
+                diagnostics.push(Diagnostic::error(
+                    command.line,
+                    format!("Unknown command {name}{hint}"),
+                ));
+            }
+            Some(spec) => {
+                diagnostics.extend(check_args(command.line, spec, args));
+                diagnostics.extend(check_types(command.line, spec, args, metadata));
+
+                // a second `shirt` replaces the first, hats stack
+                let sets_one_thing = !spec.repeat && spec.args.len() > 1;
+                if let Some(target) = args.first().filter(|_| sets_one_thing && !duplicate) {
+                    let key = (spec.name.clone(), target.to_lowercase());
+                    match set.get(&key) {
+                        Some(first) => diagnostics.push(Diagnostic::warning(
+                            command.line,
+                            format!(
+                                "{} is also set on line {}, only the last one sticks",
+                                spec.name,
+                                first + 1
+                            ),
+                        )),
+                        None => {
+                            set.insert(key, command.line);
+                        }
+                    }
+                }
+            }
+        }
+
+        let parts = if profile.split_long {
+            split(command, profile.max_length, profile.mode, catalog)
+        } else {
+            vec![command.clone()]
+        };
+        if let Some(long) = parts
+            .iter()
+            .map(|part| part.for_mode(profile.mode).chars().count())
+            .find(|len| *len > profile.max_length)
+        {
+            diagnostics.push(Diagnostic::error(
+                command.line,
+                format!(
+                    "{long} chars is over the {} char limit and will be cut off",
+                    profile.max_length
+                ),
+            ));
+        }
+    }
+
+    diagnostics.sort_by_key(|d| d.line);
+    diagnostics
+}
+
+fn check_args(line: usize, spec: &Spec, args: &[&str]) -> Vec<Diagnostic> {
+    if args.is_empty() && spec.args.first() == Some(&ArgKind::Player) {
+        return vec![Diagnostic::error(
+            line,
+            format!("{} needs a target like me", spec.name),
+        )];
+    }
+    let too_many = !args.is_empty() && spec.arg(args.len() - 1).is_none();
+    if args.len() < spec.args.len() || too_many {
+        return vec![Diagnostic::error(
+            line,
+            format!(
+                "{} takes {}, got {} argument(s)",
+                spec.name,
+                spec.usage(),
+                args.len()
+            ),
+        )];
+    }
+
+    args.iter()
+        .enumerate()
+        .filter_map(|(index, arg)| {
+            let kind = spec.arg(index)?;
+            (!kind.accepts(arg))
+                .then(|| Diagnostic::error(line, format!("{arg} isn't a valid {}", kind.label())))
+        })
+        .collect()
+}
+
+// only IDs the imported metadata knows are checked
+fn check_types(line: usize, spec: &Spec, args: &[&str], metadata: &Metadata) -> Vec<Diagnostic> {
+    args.iter()
+        .enumerate()
+        .filter(|(index, _)| spec.arg(*index) == Some(ArgKind::AssetId))
+        .filter_map(|(_, arg)| {
+            let asset = metadata.get(arg.parse().ok()?)?;
+            (!asset.kind.is_empty() && !spec.takes_type(&asset.kind)).then(|| {
+                Diagnostic::warning(
+                    line,
+                    format!(
+                        "{arg} is {}, a {}, which {} doesn't take",
+                        asset.name, asset.kind, spec.name
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+// the catalog name within two edits, for typos like `hatt`
+fn closest<'a>(name: &str, catalog: &'a Catalog) -> Option<&'a str> {
+    let name = name.to_lowercase();
+    catalog
+        .commands
+        .iter()
+        .map(|spec| {
+            (
+                distance(&name, &spec.name.to_lowercase()),
+                spec.name.as_str(),
+            )
+        })
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name)
+}
+
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn check(text: &str, profile: &Profile) -> Vec<String> {
+        let script = parse(text);
+        lint(
+            &script,
+            &script.apply(profile),
+            &Catalog::bundled(),
+            &Metadata::default(),
+        )
+        .iter()
+        .map(Diagnostic::to_string)
+        .collect()
+    }
+
+    fn messages(text: &str) -> Vec<String> {
+        check(text, &Profile::default())
+    }
+
+    #[test]
+    fn unknown_commands_suggest_the_closest() {
+        assert_eq!(
+            messages("hatt me 1\n"),
+            ["error on line 1: Unknown command hatt, did you mean hat?"]
+        );
+        assert_eq!(
+            messages("xyzzy me 1\n"),
+            ["error on line 1: Unknown command xyzzy"]
+        );
+        assert!(messages("permhats me 1\n").is_empty());
+    }
+
+    #[test]
+    fn arguments_are_counted_and_checked() {
+        assert_eq!(
+            messages("shirt\n"),
+            ["error on line 1: shirt needs a target like me"]
+        );
+        let usage = Catalog::bundled().find("shirt").unwrap().usage();
+        assert_eq!(
+            messages("shirt me\n"),
+            [format!(
+                "error on line 1: shirt takes {usage}, got 1 argument(s)"
+            )]
+        );
+        assert_eq!(
+            messages("shirt me 1 2\n"),
+            [format!(
+                "error on line 1: shirt takes {usage}, got 3 argument(s)"
+            )]
+        );
+        assert_eq!(
+            messages("shirt me abc\n"),
+            ["error on line 1: abc isn't a valid asset id"]
+        );
+        // hats repeat, any number of IDs is fine
+        assert!(messages("hat me 1 2 3\n").is_empty());
+    }
+
+    #[test]
+    fn duplicates_and_overridden_settings_are_told_apart() {
+        assert_eq!(
+            messages("shirt me 1\nSHIRT me 1\n"),
+            ["warning on line 2: Same command as line 1"]
+        );
+        assert_eq!(
+            messages("shirt me 1\nshirt me 2\nshirt others 2\n"),
+            ["warning on line 2: shirt is also set on line 1, only the last one sticks"]
+        );
+        assert!(messages("hat me 1\nhat me 2\n").is_empty());
+    }
+
+    #[test]
+    fn length_counts_what_is_sent() {
+        let profile = Profile {
+            max_length: 20,
+            ..Profile::default()
+        };
+        let long = "hat me 1111111 2222222 3333333\n";
+        assert_eq!(
+            check(long, &profile),
+            ["error on line 1: 30 chars is over the 20 char limit and will be cut off"]
+        );
+
+        let split = Profile {
+            split_long: true,
+            ..profile
+        };
+        assert!(check(long, &split).is_empty());
+    }
+
+    #[test]
+    fn distance_counts_edits() {
+        assert_eq!(distance("hat", "hat"), 0);
+        assert_eq!(distance("hatt", "hat"), 1);
+        assert_eq!(distance("hta", "hat"), 2);
+        assert_eq!(distance("", "face"), 4);
+        assert_eq!(distance("shirt", "skirt"), 1);
+    }
+}
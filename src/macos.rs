@@ -1,4 +1,14 @@
-use core_graphics::event::CGEventFlags;
+use crate::{dispatch::Job, settings::Binding, utils::log_message};
+use core_foundation::runloop::{CFRunLoop, kCFRunLoopCommonModes};
+use core_graphics::event::{
+    CGEvent, CGEventFlags, CGEventTap, CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement,
+    CGEventType, CallbackResult,
+};
+use eframe::egui;
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex, mpsc},
+};
 
 pub fn flags_to_strings(flags: CGEventFlags) -> Vec<String> {
     let mut parts = Vec::new();
@@ -87,3 +97,106 @@ pub fn keycode_to_string(keycode: u64) -> String {
     }
     .to_string()
 }
+
+pub fn string_to_keycode(name: &str) -> Option<u64> {
+    (0..128).find(|&keycode| keycode_to_string(keycode).eq_ignore_ascii_case(name))
+}
+
+pub fn listen_bindings(
+    bindings: &[Binding],
+    tx: mpsc::Sender<Job>,
+    log: Arc<Mutex<Vec<String>>>,
+    ctx: egui::Context,
+) -> Result<(), String> {
+    let mut targets = Vec::new();
+    for binding in bindings {
+        let mut keys = HashSet::new();
+        let mut flags = CGEventFlags::empty();
+        for name in &binding.keys {
+            match name.as_str() {
+                "Cmd" => flags |= CGEventFlags::CGEventFlagCommand,
+                "Shift" => flags |= CGEventFlags::CGEventFlagShift,
+                "Ctrl" => flags |= CGEventFlags::CGEventFlagControl,
+                "Option" => flags |= CGEventFlags::CGEventFlagAlternate,
+                _ => {
+                    keys.insert(string_to_keycode(name).ok_or(format!("Unknown key {name}"))?);
+                }
+            }
+        }
+        if keys.is_empty() && flags.is_empty() {
+            return Err(format!(
+                "Binding for {} has no keys",
+                binding.morph.display()
+            ));
+        }
+        targets.push((keys, flags, binding.morph.clone(), false));
+    }
+
+    std::thread::spawn(move || {
+        let callback_log = Arc::clone(&log);
+        let callback_ctx = ctx.clone();
+        let pressed_keys = Mutex::new(HashSet::<u64>::new());
+        let targets = Mutex::new(targets);
+
+        let callback = move |_, event_type, event: &CGEvent| {
+            let keycode = event.get_integer_value_field(9) as u64;
+            let current_flags = event.get_flags();
+            let mut keys = pressed_keys.lock().unwrap();
+            match event_type {
+                CGEventType::KeyDown => {
+                    keys.insert(keycode);
+                }
+                CGEventType::KeyUp => {
+                    keys.remove(&keycode);
+                }
+                CGEventType::FlagsChanged => {}
+                _ => return CallbackResult::Keep,
+            }
+
+            for (target_keys, target_flags, morph, is_down) in targets.lock().unwrap().iter_mut() {
+                let is_met = keys.is_superset(target_keys) && current_flags.contains(*target_flags);
+                if is_met && !*is_down {
+                    log_message(
+                        &callback_log,
+                        &format!("Hotkey PRESSED for {}!", morph.display()),
+                        &callback_ctx,
+                    );
+                    let _ = tx.send(Job::File {
+                        path: morph.clone(),
+                        force: false,
+                    });
+                }
+                *is_down = is_met;
+            }
+            CallbackResult::Keep
+        };
+
+        let events = vec![
+            CGEventType::KeyDown,
+            CGEventType::KeyUp,
+            CGEventType::FlagsChanged,
+        ];
+        if let Ok(tap) = CGEventTap::new(
+            CGEventTapLocation::HID,
+            CGEventTapPlacement::HeadInsertEventTap,
+            CGEventTapOptions::Default,
+            events,
+            callback,
+        ) {
+            log_message(&log, "Event tap created successfully.", &ctx);
+            unsafe {
+                let loop_source = tap.mach_port().create_runloop_source(0).unwrap();
+                CFRunLoop::get_current().add_source(&loop_source, kCFRunLoopCommonModes);
+                tap.enable();
+                CFRunLoop::run_current();
+            }
+        } else {
+            log_message(
+                &log,
+                "Failed to create event tap. Check macOS permissions.",
+                &ctx,
+            );
+        }
+    });
+    Ok(())
+}
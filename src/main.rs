@@ -1,9 +1,36 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
-use auto_morph::app::App;
+use auto_morph::{app::App, cli, ipc};
 use eframe::egui;
 
+#[cfg(target_os = "windows")]
+unsafe extern "system" {
+    fn AttachConsole(process_id: u32) -> i32;
+}
+
 fn main() -> eframe::Result {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        // release builds have no console of their own, borrow the terminal's
+        #[cfg(target_os = "windows")]
+        unsafe {
+            AttachConsole(u32::MAX);
+        }
+        if let Some(code) = cli::main(&args) {
+            std::process::exit(code);
+        }
+    }
+
+    // a second window would start a second hotkey listener, hand over to the first one
+    let listener = match ipc::claim() {
+        Ok(Some(listener)) => listener,
+        Ok(None) => forward(&args),
+        Err(error) => {
+            eprintln!("Couldn't open the control socket: {error}");
+            std::process::exit(1);
+        }
+    };
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1100.0, 700.0])
@@ -19,6 +46,24 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "Blazingly fast SCP:RP morpher",
         options,
-        Box::new(|cc| Ok(Box::new(<App>::new(cc)))),
+        Box::new(|cc| Ok(Box::new(App::new(cc, args, listener)))),
     )
 }
+
+// hands the arguments to the running instance and exits
+fn forward(args: &[String]) -> ! {
+    let Some(response) = ipc::forward(args) else {
+        eprintln!("auto_morph is already running but didn't answer");
+        std::process::exit(1);
+    };
+    if response["ok"] != true {
+        eprintln!(
+            "{}",
+            response["error"]
+                .as_str()
+                .unwrap_or("auto_morph is already running")
+        );
+        std::process::exit(1);
+    }
+    std::process::exit(0);
+}
@@ -0,0 +1,136 @@
+// git's markers, lint flags them as unknown commands so a conflicted morph can't be run as is
+const MINE: &str = "<<<<<<< editor";
+const SPLIT: &str = "=======";
+const THEIRS: &str = ">>>>>>> disk";
+
+pub struct Merged {
+    pub text: String,
+    pub conflicts: usize,
+}
+
+// a three-way merge of the editor and disk texts, both changed from `base`. Lines only one side
+// touched take that side's version, lines both changed differently are kept between markers
+pub fn merge(base: &str, mine: &str, theirs: &str) -> Merged {
+    let base_lines: Vec<&str> = base.lines().collect();
+    let mine_lines: Vec<&str> = mine.lines().collect();
+    let theirs_lines: Vec<&str> = theirs.lines().collect();
+    let to_mine = matches(&base_lines, &mine_lines);
+    let to_theirs = matches(&base_lines, &theirs_lines);
+
+    let mut merged: Vec<&str> = Vec::new();
+    let mut conflicts = 0;
+    let (mut b, mut m, mut t) = (0, 0, 0);
+    // base lines both sides kept split the texts into chunks, the end counts as one
+    for i in 0..=base_lines.len() {
+        let stable = match (to_mine.get(i), to_theirs.get(i)) {
+            (Some(Some(in_mine)), Some(Some(in_theirs))) => (*in_mine, *in_theirs),
+            (None, None) => (mine_lines.len(), theirs_lines.len()),
+            _ => continue,
+        };
+        let base_chunk = &base_lines[b..i];
+        let mine_chunk = &mine_lines[m..stable.0];
+        let theirs_chunk = &theirs_lines[t..stable.1];
+        if mine_chunk == base_chunk {
+            merged.extend(theirs_chunk);
+        } else if theirs_chunk == base_chunk || theirs_chunk == mine_chunk {
+            merged.extend(mine_chunk);
+        } else {
+            conflicts += 1;
+            merged.push(MINE);
+            merged.extend(mine_chunk);
+            merged.push(SPLIT);
+            merged.extend(theirs_chunk);
+            merged.push(THEIRS);
+        }
+
+        merged.extend(base_lines.get(i));
+        (b, m, t) = (i + 1, stable.0 + 1, stable.1 + 1);
+    }
+
+    // the editor's line endings win, disk's when the editor is empty
+    let style = if mine.is_empty() { theirs } else { mine };
+    let ending = if style.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut text = merged.join(ending);
+    if style.ends_with('\n') && !merged.is_empty() {
+        text.push_str(ending);
+    }
+    Merged { text, conflicts }
+}
+
+// for each line of `a`, where it sits in `b` along a longest common subsequence
+fn matches(a: &[&str], b: &[&str]) -> Vec<Option<usize>> {
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut found = vec![None; a.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            found[i] = Some(j);
+            (i, j) = (i + 1, j + 1);
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_from_both_sides_are_kept() {
+        let base = "hat me 1\nshirt me 2\npants me 3\n";
+        let mine = "hat me 10\nshirt me 2\npants me 3\n";
+        let theirs = "hat me 1\nshirt me 2\npants me 30\nface me 4\n";
+        let merged = merge(base, mine, theirs);
+        assert_eq!(
+            merged.text,
+            "hat me 10\nshirt me 2\npants me 30\nface me 4\n"
+        );
+        assert_eq!(merged.conflicts, 0);
+    }
+
+    #[test]
+    fn deletions_are_merged_too() {
+        let base = "hat me 1\nshirt me 2\npants me 3\n";
+        let mine = "hat me 1\npants me 3\n";
+        let theirs = "hat me 1\nshirt me 2\npants me 3\nface me 4\n";
+        assert_eq!(
+            merge(base, mine, theirs).text,
+            "hat me 1\npants me 3\nface me 4\n"
+        );
+    }
+
+    #[test]
+    fn clashing_edits_are_marked() {
+        let merged = merge(
+            "hat me 1\nshirt me 2",
+            "hat me 5\nshirt me 2",
+            "hat me 6\nshirt me 2",
+        );
+        assert_eq!(
+            merged.text,
+            "<<<<<<< editor\nhat me 5\n=======\nhat me 6\n>>>>>>> disk\nshirt me 2"
+        );
+        assert_eq!(merged.conflicts, 1);
+    }
+
+    #[test]
+    fn keeps_crlf_and_the_trailing_newline() {
+        let merged = merge("a\r\nb\r\n", "a\r\nb\r\nc\r\n", "x\r\nb\r\n");
+        assert_eq!(merged.text, "x\r\nb\r\nc\r\n");
+        assert_eq!(merge("a\n", "a\nb", "a\n").text, "a\nb");
+    }
+}
@@ -0,0 +1,441 @@
+use crate::{
+    catalog::{ArgKind, Catalog},
+    profile::{Mode, Profile},
+};
+use std::ops::Range;
+
+pub const DIRECTIVES: &[&str] = &["mode", "reset"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Command {
+    pub line: usize,
+    pub text: String,
+}
+
+impl Command {
+    pub fn body(&self) -> &str {
+        self.text.strip_prefix(':').unwrap_or(&self.text)
+    }
+
+    pub fn for_mode(&self, mode: Mode) -> String {
+        match mode {
+            Mode::CommandBar => self.body().to_owned(),
+            Mode::Chat => format!(":{}", self.body()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Prefix,
+    Command,
+    Target,
+    AssetId,
+    Argument,
+    Comment,
+    Directive,
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    // byte range into the line
+    pub range: Range<usize>,
+    pub kind: TokenKind,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Script {
+    pub mode: Option<Mode>,
+    pub is_run: bool,
+    pub commands: Vec<Command>,
+    // `#reset` lines, the morph's own reset script
+    pub reset: Vec<String>,
+}
+
+impl Script {
+    // directives in the script override the active profile
+    pub fn apply(&self, profile: &Profile) -> Profile {
+        let mut profile = profile.clone();
+        if let Some(mode) = self.mode {
+            profile.mode = mode;
+        }
+        profile
+    }
+
+    // what actually gets submitted, one entry per open/paste/enter cycle
+    pub fn submissions(&self, profile: &Profile, catalog: &Catalog) -> Vec<Command> {
+        if self.is_run {
+            return self.commands.clone();
+        }
+
+        let mut commands = self.commands.clone();
+        if profile.split_long {
+            commands = commands
+                .iter()
+                .flat_map(|command| split(command, profile.max_length, profile.mode, catalog))
+                .collect();
+        }
+        if profile.batch {
+            commands = batch(
+                &commands,
+                &profile.separator,
+                profile.max_length,
+                profile.mode,
+            );
+        }
+        commands
+    }
+}
+
+pub fn parse(src: &str) -> Script {
+    let mut script = Script::default();
+    let lines: Vec<&str> = src.lines().collect();
+
+    for (i, raw) in lines.iter().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix('#') {
+            match directive(rest) {
+                Some(("mode", value)) => script.mode = Mode::from_name(value),
+                Some(("reset", value)) if !value.is_empty() => script.reset.push(value.to_owned()),
+                _ => {}
+            }
+            continue;
+        }
+
+        if script.commands.is_empty() && is_run(line) {
+            // run scripts are sent as a single command, newlines included
+            script.is_run = true;
+            script.commands.push(Command {
+                line: i,
+                text: lines[i..].join("\n").trim().to_owned(),
+            });
+            break;
+        }
+
+        script.commands.push(Command {
+            line: i,
+            text: line.to_owned(),
+        });
+    }
+
+    script
+}
+
+// `run` or `:run` as its own word, so `runspeed me 2` is a normal command
+fn is_run(line: &str) -> bool {
+    let body = line.strip_prefix(':').unwrap_or(line);
+    body.split_whitespace().next() == Some("run")
+}
+
+// `#mode chat` and `#reset unchar me` are directives, any other `#` line is a comment
+pub fn directive(rest: &str) -> Option<(&str, &str)> {
+    let rest = rest.trim_start();
+    let (name, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    DIRECTIVES
+        .iter()
+        .find(|d| d.eq_ignore_ascii_case(name))
+        .map(|d| (*d, value.trim()))
+}
+
+// classifies one line of a morph for highlighting, run scripts aside
+pub fn tokens(line: &str, catalog: &Catalog) -> Vec<Token> {
+    let mut words = words(line).into_iter();
+    let Some(first) = words.next() else {
+        return Vec::new();
+    };
+
+    if line[first.clone()].starts_with('#') {
+        let start = first.start;
+        let kind = match directive(&line[start + 1..]) {
+            Some(("mode", value)) if Mode::from_name(value).is_none() => TokenKind::Error,
+            Some(_) => TokenKind::Directive,
+            None => TokenKind::Comment,
+        };
+        return vec![Token {
+            range: start..line.trim_end().len(),
+            kind,
+        }];
+    }
+
+    let mut tokens = Vec::new();
+    let mut command = first;
+    if line[command.clone()].starts_with(':') {
+        tokens.push(Token {
+            range: command.start..command.start + 1,
+            kind: TokenKind::Prefix,
+        });
+        command.start += 1;
+    }
+    let mut spec = None;
+    if !command.is_empty() {
+        spec = catalog.find(&line[command.clone()]);
+        tokens.push(Token {
+            range: command,
+            kind: match spec {
+                Some(_) => TokenKind::Command,
+                None => TokenKind::Error,
+            },
+        });
+    }
+
+    for (index, range) in words.enumerate() {
+        let word = &line[range.clone()];
+        let kind = match spec.map(|spec| spec.arg(index)) {
+            Some(Some(kind)) if !kind.accepts(word) => TokenKind::Error,
+            Some(Some(ArgKind::Player)) => TokenKind::Target,
+            Some(Some(ArgKind::AssetId | ArgKind::Number)) => TokenKind::AssetId,
+            Some(_) => TokenKind::Argument,
+            // unknown commands still get their target and ids picked out
+            None if index == 0 => TokenKind::Target,
+            None if ArgKind::AssetId.accepts(word) => TokenKind::AssetId,
+            None => TokenKind::Argument,
+        };
+        tokens.push(Token { range, kind });
+    }
+    tokens
+}
+
+fn words(line: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(from)) => {
+                words.push(from..i);
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(from) = start {
+        words.push(from..line.len());
+    }
+    words
+}
+
+// packs commands into submissions of at most `max_length` chars as they're sent, every command
+// keeping the prefix `mode` needs
+pub fn batch(commands: &[Command], separator: &str, max_length: usize, mode: Mode) -> Vec<Command> {
+    let mut batches: Vec<Command> = Vec::new();
+    let mut current: Option<Command> = None;
+    let separator_len = separator.chars().count();
+
+    for command in commands {
+        let piece = command.for_mode(mode);
+        let piece_len = piece.chars().count();
+        current = match current.take() {
+            Some(mut open)
+                if open.text.chars().count() + separator_len + piece_len <= max_length =>
+            {
+                open.text.push_str(separator);
+                open.text.push_str(&piece);
+                Some(open)
+            }
+            full => {
+                batches.extend(full);
+                Some(Command {
+                    line: command.line,
+                    text: piece,
+                })
+            }
+        };
+    }
+
+    batches.extend(current);
+    batches
+}
+
+// `hat me 1 2 3` becomes `hat me 1 2` + `hat me 3` when the limit is hit, only for commands
+// whose last argument repeats, everything else goes out whole for lint to flag
+pub fn split(command: &Command, max_length: usize, mode: Mode, catalog: &Catalog) -> Vec<Command> {
+    // the limit is on the text as sent, with its `:` in chat mode
+    let prefix = command.for_mode(mode).len() - command.body().len();
+    if command.for_mode(mode).chars().count() <= max_length {
+        return vec![command.clone()];
+    }
+
+    let tokens: Vec<&str> = command.body().split_whitespace().collect();
+    let Some(spec) = tokens.first().and_then(|name| catalog.find(name)) else {
+        return vec![command.clone()];
+    };
+    let fixed = spec.args.len();
+    if !spec.repeat || spec.args.contains(&ArgKind::Text) || tokens.len() <= fixed + 1 {
+        return vec![command.clone()];
+    }
+
+    // the name and every argument before the repeated one
+    let head = tokens[..fixed].join(" ");
+    let head_len = prefix + head.chars().count();
+    let mut parts: Vec<Command> = Vec::new();
+    let mut current = head.clone();
+    let mut current_len = head_len;
+    for arg in &tokens[fixed..] {
+        let arg_len = arg.chars().count();
+        if current_len > head_len && current_len + 1 + arg_len > max_length {
+            parts.push(Command {
+                line: command.line,
+                text: std::mem::replace(&mut current, head.clone()),
+            });
+            current_len = head_len;
+        }
+        current.push(' ');
+        current.push_str(arg);
+        current_len += 1 + arg_len;
+    }
+    parts.push(Command {
+        line: command.line,
+        text: current,
+    });
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(lines: &[&str]) -> Vec<Command> {
+        lines
+            .iter()
+            .enumerate()
+            .map(|(line, text)| Command {
+                line,
+                text: text.to_string(),
+            })
+            .collect()
+    }
+
+    fn texts(commands: &[Command], mode: Mode) -> Vec<String> {
+        commands.iter().map(|c| c.for_mode(mode)).collect()
+    }
+
+    #[test]
+    fn batch_prefixes_every_command_in_chat() {
+        let batched = batch(
+            &commands(&["hat me 1", ":shirt me 2"]),
+            " | ",
+            200,
+            Mode::Chat,
+        );
+        assert_eq!(texts(&batched, Mode::Chat), [":hat me 1 | :shirt me 2"]);
+
+        let batched = batch(
+            &commands(&["hat me 1", ":shirt me 2"]),
+            " | ",
+            200,
+            Mode::CommandBar,
+        );
+        assert_eq!(texts(&batched, Mode::CommandBar), ["hat me 1 | shirt me 2"]);
+    }
+
+    #[test]
+    fn batch_limit_counts_sent_chars() {
+        // ":hat me 1 | :hat me 2" is exactly 21 chars
+        let lines = commands(&["hat me 1", "hat me 2"]);
+        assert_eq!(batch(&lines, " | ", 21, Mode::Chat).len(), 1);
+        assert_eq!(batch(&lines, " | ", 20, Mode::Chat).len(), 2);
+        // "hat me 1 | hat me 2" is 19
+        assert_eq!(batch(&lines, " | ", 19, Mode::CommandBar).len(), 1);
+
+        // ten chars of two bytes each
+        let lines = commands(&["name me éééééééééé", "name me é"]);
+        let batched = batch(&lines, " | ", 30, Mode::CommandBar);
+        assert_eq!(
+            texts(&batched, Mode::CommandBar),
+            ["name me éééééééééé | name me é"]
+        );
+    }
+
+    #[test]
+    fn split_only_splits_repeated_arguments() {
+        let catalog = Catalog::bundled();
+        let hat = Command {
+            line: 0,
+            text: "hat me 111 222 333".to_owned(),
+        };
+        let parts = split(&hat, 14, Mode::CommandBar, &catalog);
+        assert_eq!(
+            texts(&parts, Mode::CommandBar),
+            ["hat me 111 222", "hat me 333"]
+        );
+        // `:hat me 111 222` is 15 chars as sent
+        let parts = split(&hat, 14, Mode::Chat, &catalog);
+        assert_eq!(
+            texts(&parts, Mode::Chat),
+            [":hat me 111", ":hat me 222", ":hat me 333"]
+        );
+        assert_eq!(split(&hat, 18, Mode::CommandBar, &catalog), [hat]);
+
+        // text is never cut into separate commands
+        let name = Command {
+            line: 0,
+            text: "name me a very long display name".to_owned(),
+        };
+        assert_eq!(split(&name, 10, Mode::CommandBar, &catalog), [name]);
+    }
+
+    fn kinds(line: &str) -> Vec<(&str, TokenKind)> {
+        tokens(line, &Catalog::bundled())
+            .into_iter()
+            .map(|token| (&line[token.range], token.kind))
+            .collect()
+    }
+
+    #[test]
+    fn tokens_classify_each_word() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds("hat me 123"),
+            [("hat", Command), ("me", Target), ("123", AssetId)]
+        );
+        assert_eq!(
+            kinds("hatt me 123"),
+            [("hatt", Error), ("me", Target), ("123", AssetId)]
+        );
+        assert_eq!(
+            kinds("shirt me abc"),
+            [("shirt", Command), ("me", Target), ("abc", Error)]
+        );
+        assert_eq!(
+            kinds("bodycolor me red"),
+            [("bodycolor", Command), ("me", Target), ("red", Argument)]
+        );
+        assert!(kinds("   ").is_empty());
+    }
+
+    #[test]
+    fn tokens_find_the_prefix() {
+        use TokenKind::*;
+        let line = "  :hat me 1";
+        let found = tokens(line, &Catalog::bundled());
+        assert_eq!(
+            found[0],
+            Token {
+                range: 2..3,
+                kind: Prefix
+            }
+        );
+        assert_eq!(
+            found[1],
+            Token {
+                range: 3..6,
+                kind: Command
+            }
+        );
+        assert_eq!(kinds(":"), [(":", Prefix)]);
+    }
+
+    #[test]
+    fn tokens_for_comments_and_directives() {
+        use TokenKind::*;
+        assert_eq!(kinds("  # a note  "), [("# a note", Comment)]);
+        assert_eq!(kinds("#mode chat"), [("#mode chat", Directive)]);
+        assert_eq!(kinds("#reset unchar me"), [("#reset unchar me", Directive)]);
+        assert_eq!(kinds("#mode bogus"), [("#mode bogus", Error)]);
+    }
+}
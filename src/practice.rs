@@ -0,0 +1,200 @@
+use crate::backend::{Backend, Step};
+use eframe::egui;
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+pub struct Practice {
+    pub open: bool,
+    // hotkey runs go to the practice bar instead of the keyboard
+    pub capture_runs: bool,
+    // how long the bar takes to accept input after opening
+    pub latency: u64,
+    pub history: Vec<String>,
+    bar: Option<(String, Instant)>,
+    started: Option<Instant>,
+}
+
+impl Default for Practice {
+    fn default() -> Self {
+        Self {
+            open: false,
+            capture_runs: true,
+            latency: 30,
+            history: Vec::new(),
+            bar: None,
+            started: None,
+        }
+    }
+}
+
+pub struct PracticeBackend {
+    practice: Arc<Mutex<Practice>>,
+    ctx: egui::Context,
+}
+
+impl PracticeBackend {
+    pub fn new(practice: Arc<Mutex<Practice>>, ctx: egui::Context) -> Self {
+        {
+            let mut practice = practice.lock().unwrap();
+            practice.started = None;
+            practice.bar = None;
+            practice.history.push("--- run started ---".to_owned());
+        }
+        Self { practice, ctx }
+    }
+}
+
+impl Backend for PracticeBackend {
+    fn perform(&mut self, step: &Step) {
+        if let Step::Wait(ms) = step {
+            std::thread::sleep(std::time::Duration::from_millis(*ms));
+            return;
+        }
+
+        let mut guard = self.practice.lock().unwrap();
+        let practice = &mut *guard;
+        let now = Instant::now();
+        let at = now
+            .duration_since(*practice.started.get_or_insert(now))
+            .as_millis();
+
+        match step {
+            // the apostrophe lands in the bar, which is why the real flow erases it
+            Step::OpenCommandBar => practice.bar = Some(("'".to_owned(), now)),
+            Step::OpenChat => practice.bar = Some((String::new(), now)),
+            Step::Erase => {
+                if let Some((text, _)) = &mut practice.bar {
+                    text.pop();
+                }
+            }
+            Step::Paste(text) | Step::Type(text, _) => match &mut practice.bar {
+                Some((input, opened))
+                    if now.duration_since(*opened).as_millis() as u64 >= practice.latency =>
+                {
+                    input.push_str(text);
+                }
+                Some(_) => practice
+                    .history
+                    .push(format!("{at:>6} ms  dropped \"{text}\", bar wasn't ready")),
+                None => practice
+                    .history
+                    .push(format!("{at:>6} ms  dropped \"{text}\", no bar open")),
+            },
+            Step::Enter => match practice.bar.take() {
+                Some((input, _)) => practice.history.push(format!("{at:>6} ms  {input}")),
+                None => practice
+                    .history
+                    .push(format!("{at:>6} ms  Enter pressed with no bar open")),
+            },
+            Step::Close => {
+                if let Some((input, _)) = practice.bar.take() {
+                    practice
+                        .history
+                        .push(format!("{at:>6} ms  closed \"{input}\" without sending"));
+                }
+            }
+            Step::Wait(_) => {}
+        }
+        drop(guard);
+
+        if let Step::Type(text, char_delay) = step {
+            std::thread::sleep(std::time::Duration::from_millis(
+                text.chars().count() as u64 * char_delay,
+            ));
+        }
+        self.ctx.request_repaint();
+    }
+}
+
+// returns true when "Run" was clicked
+pub fn window(ctx: &egui::Context, practice: &Arc<Mutex<Practice>>) -> bool {
+    let mut practice = practice.lock().unwrap();
+    let mut open = practice.open;
+    let mut run = false;
+
+    egui::Window::new("Practice")
+        .open(&mut open)
+        .default_size([500.0, 350.0])
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut practice.capture_runs, "Send runs here");
+                ui.label("Bar latency (ms):");
+                ui.add(egui::DragValue::new(&mut practice.latency).range(0..=500));
+            });
+            ui.horizontal(|ui| {
+                run = ui
+                    .add_enabled(practice.capture_runs, egui::Button::new("Run armed morph"))
+                    .clicked();
+                if ui.button("Clear").clicked() {
+                    practice.history.clear();
+                }
+            });
+            ui.separator();
+
+            let bar = match &practice.bar {
+                Some((text, _)) => format!("> {text}"),
+                None => "(bar closed)".to_owned(),
+            };
+            ui.monospace(bar);
+            ui.separator();
+
+            egui::ScrollArea::vertical()
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for line in &practice.history {
+                        ui.monospace(line);
+                    }
+                });
+        });
+
+    practice.open = open;
+    run
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        catalog::Catalog,
+        dispatch::{Dispatcher, IS_SIMULATING, Job, SIMULATING_TESTS},
+    };
+    use std::sync::{atomic::Ordering, mpsc};
+
+    #[test]
+    fn practice_runs_leave_the_applied_morph_alone() {
+        let _turn = SIMULATING_TESTS.lock().unwrap_or_else(|e| e.into_inner());
+        IS_SIMULATING.store(false, Ordering::SeqCst);
+        let practice = Arc::new(Mutex::new(Practice {
+            open: true,
+            ..Practice::default()
+        }));
+        let applied = Arc::default();
+        let (jobs, queued) = mpsc::channel();
+        let running = Dispatcher {
+            cmds: Arc::new(Mutex::new("hat me 1\n".to_owned())),
+            delay: Arc::new(Mutex::new("40".to_owned())),
+            profile: Arc::default(),
+            catalog: Catalog::bundled(),
+            applied: Arc::clone(&applied),
+            practice: Some(Arc::clone(&practice)),
+            log: Arc::new(Mutex::new(Vec::new())),
+            ctx: egui::Context::default(),
+        }
+        .spawn(queued);
+
+        jobs.send(Job::Armed).unwrap();
+        let run = loop {
+            if let Some(run) = running.lock().unwrap().take() {
+                break run;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        };
+        run.join().unwrap();
+
+        let history = practice.lock().unwrap().history.clone();
+        assert!(history.iter().any(|line| line.ends_with("  hat me 1")));
+        assert!(applied.lock().unwrap().is_none());
+    }
+}
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    // Opens the command bar with the apostrophe key, sends the line without its `:`
+    CommandBar,
+    // Opens chat with `/`, sends the line with its `:` prefix
+    Chat,
+}
+
+impl Mode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "command" | "commandbar" | "command_bar" | "bar" => Some(Mode::CommandBar),
+            "chat" => Some(Mode::Chat),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Mode::CommandBar => "Command bar",
+            Mode::Chat => "Chat",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Input {
+    // Copies each line to the clipboard and pastes it
+    Paste,
+    // Types each line character by character, leaving the clipboard alone
+    Type,
+}
+
+impl Input {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Input::Paste => "Paste",
+            Input::Type => "Type",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    pub mode: Mode,
+    pub input: Input,
+    pub char_delay: u64,
+    // packs consecutive lines into one submission joined by `separator`
+    pub batch: bool,
+    pub separator: String,
+    pub max_length: usize,
+    // splits overlong argument lists into several commands
+    pub split_long: bool,
+    // used by "Reset" instead of deriving one from the morph when set
+    pub reset: String,
+    // runs the reset before every morph
+    pub reset_first: bool,
+}
+
+impl Profile {
+    pub fn builtin() -> Vec<Profile> {
+        vec![
+            Profile::default(),
+            Profile {
+                name: "Chat only".to_owned(),
+                mode: Mode::Chat,
+                ..Profile::default()
+            },
+            Profile {
+                name: "Typed (no clipboard)".to_owned(),
+                input: Input::Type,
+                ..Profile::default()
+            },
+        ]
+    }
+
+    // `next` as it's defined, but input settings changed this session (away from what `profiles`
+    // says the current one has) come along
+    pub fn switch_to(&self, next: &Profile, profiles: &[Profile]) -> Profile {
+        let mut switched = next.clone();
+        let changed = profiles
+            .iter()
+            .find(|saved| saved.name == self.name)
+            .is_some_and(|saved| saved.input != self.input || saved.char_delay != self.char_delay);
+        if changed {
+            switched.input = self.input;
+            switched.char_delay = self.char_delay;
+        }
+        switched
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: "Command bar".to_owned(),
+            mode: Mode::CommandBar,
+            input: Input::Paste,
+            char_delay: 5,
+            batch: false,
+            separator: " | ".to_owned(),
+            max_length: 200,
+            split_long: false,
+            reset: String::new(),
+            reset_first: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switching_keeps_input_settings_changed_this_session() {
+        let profiles = Profile::builtin();
+        let chat = &profiles[1];
+
+        let untouched = profiles[0].switch_to(&profiles[2], &profiles);
+        assert_eq!(untouched.input, Input::Type);
+
+        let mut typed = profiles[0].clone();
+        typed.input = Input::Type;
+        typed.char_delay = 30;
+        let switched = typed.switch_to(chat, &profiles);
+        assert_eq!(switched.mode, Mode::Chat);
+        assert_eq!((switched.input, switched.char_delay), (Input::Type, 30));
+    }
+}
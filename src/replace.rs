@@ -0,0 +1,166 @@
+use regex::{NoExpand, Regex, RegexBuilder};
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    pub find: String,
+    pub replace: String,
+    // `$1` in the replacement refers to a group when this is set
+    pub regex: bool,
+    pub match_case: bool,
+    // only matches standing on their own, so `123` leaves `41234` alone
+    pub whole_word: bool,
+}
+
+// asset ids are all digits, the usual case where a partial match is wrong
+pub fn is_id(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_digit())
+}
+
+impl Query {
+    pub fn compile(&self) -> Result<Replacer, String> {
+        if self.find.is_empty() {
+            return Err("Nothing to find".to_owned());
+        }
+        let pattern = if self.regex {
+            self.find.clone()
+        } else {
+            regex::escape(&self.find)
+        };
+        let pattern = if self.whole_word {
+            format!(r"\b(?:{pattern})\b")
+        } else {
+            pattern
+        };
+        let pattern = RegexBuilder::new(&pattern)
+            .case_insensitive(!self.match_case)
+            .build()
+            .map_err(|e| format!("Invalid pattern: {e}"))?;
+        Ok(Replacer {
+            pattern,
+            replacement: self.replace.clone(),
+            expand: self.regex,
+        })
+    }
+}
+
+// a line with at least one match, as it is and as it would be
+#[derive(Clone, Debug)]
+pub struct Hit {
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct FileHits {
+    pub path: PathBuf,
+    pub hits: Vec<Hit>,
+    // ticked in the preview to be rewritten
+    pub selected: bool,
+}
+
+pub struct Replacer {
+    pattern: Regex,
+    replacement: String,
+    expand: bool,
+}
+
+impl Replacer {
+    fn line<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        if self.expand {
+            self.pattern.replace_all(line, self.replacement.as_str())
+        } else {
+            self.pattern.replace_all(line, NoExpand(&self.replacement))
+        }
+    }
+
+    pub fn hits(&self, text: &str) -> Vec<Hit> {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| self.pattern.is_match(line))
+            .map(|(i, line)| Hit {
+                line: i,
+                before: line.to_owned(),
+                after: self.line(line).into_owned(),
+            })
+            .collect()
+    }
+
+    // matches never span lines, and line endings are kept as they are
+    pub fn replace(&self, text: &str) -> (String, usize) {
+        let mut replaced = String::with_capacity(text.len());
+        let mut count = 0;
+        for chunk in text.split_inclusive('\n') {
+            let line = chunk.trim_end_matches(['\r', '\n']);
+            count += self.pattern.find_iter(line).count();
+            replaced.push_str(&self.line(line));
+            replaced.push_str(&chunk[line.len()..]);
+        }
+        (replaced, count)
+    }
+
+    // files that can't be read are left out
+    pub fn search(&self, files: &[PathBuf]) -> Vec<FileHits> {
+        files
+            .iter()
+            .filter_map(|path| {
+                let hits = self.hits(&fs::read_to_string(path).ok()?);
+                (!hits.is_empty()).then(|| FileHits {
+                    path: path.clone(),
+                    hits,
+                    selected: true,
+                })
+            })
+            .collect()
+    }
+
+    // rewrites one file, returning the number of replacements
+    pub fn apply(&self, path: &Path) -> Result<usize, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read {}: {e}", path.display()))?;
+        let (replaced, count) = self.replace(&text);
+        if count > 0 {
+            fs::write(path, replaced)
+                .map_err(|e| format!("Couldn't write {}: {e}", path.display()))?;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(find: &str, replace: &str, whole_word: bool) -> Replacer {
+        Query {
+            find: find.to_owned(),
+            replace: replace.to_owned(),
+            whole_word,
+            ..Query::default()
+        }
+        .compile()
+        .unwrap()
+    }
+
+    #[test]
+    fn whole_word_leaves_longer_ids_alone() {
+        let text = "hat me 123\nhat me 41234\n";
+        assert_eq!(
+            query("123", "9", true).replace(text),
+            ("hat me 9\nhat me 41234\n".to_owned(), 1)
+        );
+        assert_eq!(query("123", "9", false).replace(text).1, 2);
+    }
+
+    #[test]
+    fn numbers_count_as_ids() {
+        assert!(is_id("12345"));
+        assert!(!is_id("12a"));
+        assert!(!is_id(""));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{
+    catalog::Catalog,
+    parser::{Script, parse},
+    profile::Profile,
+};
+use std::sync::{Arc, Mutex};
+
+// used when there's nothing to derive a reset from
+pub const FALLBACK: &str = "unpermall me\nunpermhats me\nunpermshirt me\nclearstartergear me";
+
+// the last morph sent to the game, what a reset before the next one has to undo
+pub type Applied = Arc<Mutex<Option<Script>>>;
+
+// undoes a morph with the catalog's `undo` commands, latest change first, then refreshes targets
+// that got changes nothing undoes
+pub fn derive(script: &Script, catalog: &Catalog) -> Vec<String> {
+    if script.is_run {
+        return Vec::new();
+    }
+
+    let mut undo: Vec<String> = Vec::new();
+    let mut refresh: Vec<String> = Vec::new();
+    for command in script.commands.iter().rev() {
+        let mut words = command.body().split_whitespace();
+        let spec = words.next().and_then(|name| catalog.find(name));
+        let (Some(spec), Some(target)) = (spec, words.next()) else {
+            continue;
+        };
+        let (lines, line) = match &spec.undo {
+            Some(name) => (&mut undo, format!("{name} {target}")),
+            // commands that only take a target are resets themselves
+            None if spec.args.len() > 1 => (&mut refresh, format!("refresh {target}")),
+            None => continue,
+        };
+        if !lines.iter().any(|l| l.eq_ignore_ascii_case(&line)) {
+            lines.push(line);
+        }
+    }
+
+    refresh.reverse();
+    undo.extend(refresh);
+    undo
+}
+
+// the morph's own `#reset` lines, else the profile's reset, else one derived from the morph,
+// else FALLBACK
+pub fn reset(script: &Script, profile: &Profile, catalog: &Catalog) -> Vec<String> {
+    if !script.reset.is_empty() {
+        return script.reset.clone();
+    }
+    if !profile.reset.trim().is_empty() {
+        return profile.reset.lines().map(str::to_owned).collect();
+    }
+    let derived = derive(script, catalog);
+    if derived.is_empty() {
+        return FALLBACK.lines().map(str::to_owned).collect();
+    }
+    derived
+}
+
+// for profiles with `reset_first`: the reset of the `last` applied morph ahead of this one's
+// commands, with nothing applied yet that's the profile's reset or FALLBACK
+pub fn then_apply(
+    script: &Script,
+    last: Option<&Script>,
+    profile: &Profile,
+    catalog: &Catalog,
+) -> Script {
+    let last = last.cloned().unwrap_or_default();
+    let reset = parse(&reset(&last, profile, catalog).join("\n"));
+    let mut applied = script.clone();
+    applied.commands = reset
+        .commands
+        .into_iter()
+        .chain(script.commands.iter().cloned())
+        .collect();
+    applied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bodies(script: &Script) -> Vec<String> {
+        script
+            .commands
+            .iter()
+            .map(|command| command.body().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn derive_undoes_the_latest_change_first() {
+        let script = parse("hat me 1\nshirt me 2\nhat others 3\nsize me 2\nunpermall me\n");
+        assert_eq!(
+            derive(&script, &Catalog::bundled()),
+            ["removehats others", "removehats me", "refresh me"]
+        );
+        assert!(derive(&parse("run\nprint(1)\n"), &Catalog::bundled()).is_empty());
+    }
+
+    #[test]
+    fn reset_prefers_the_morph_then_the_profile() {
+        let catalog = Catalog::bundled();
+        let mut profile = Profile::default();
+        let own = parse("#reset unchar me\nhat me 1\n");
+        let plain = parse("hat me 1\n");
+
+        profile.reset = "unpermall me\n".to_owned();
+        assert_eq!(reset(&own, &profile, &catalog), ["unchar me"]);
+        assert_eq!(reset(&plain, &profile, &catalog), ["unpermall me"]);
+
+        profile.reset.clear();
+        assert_eq!(reset(&plain, &profile, &catalog), ["removehats me"]);
+        assert_eq!(
+            reset(&Script::default(), &profile, &catalog).join("\n"),
+            FALLBACK
+        );
+    }
+
+    #[test]
+    fn then_apply_undoes_the_last_morph() {
+        let catalog = Catalog::bundled();
+        let profile = Profile::default();
+        let next = parse("shirt me 2\n");
+        let last = parse("hat me 1\n");
+
+        let applied = then_apply(&next, Some(&last), &profile, &catalog);
+        assert_eq!(bodies(&applied), ["removehats me", "shirt me 2"]);
+
+        let first = then_apply(&next, None, &profile, &catalog);
+        let mut expected: Vec<&str> = FALLBACK.lines().collect();
+        expected.push("shirt me 2");
+        assert_eq!(bodies(&first), expected);
+    }
+}
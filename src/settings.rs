@@ -0,0 +1,105 @@
+use crate::profile::Profile;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Binding {
+    // rdev names on Windows (`ShiftRight`, `KeyM`), display names on macOS (`Cmd`, `M`)
+    pub keys: Vec<String>,
+    pub morph: PathBuf,
+}
+
+// the loopback HTTP API is only started when this is present
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    pub port: u16,
+    pub token: String,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            port: 8765,
+            token: String::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub delay: u64,
+    pub profile: String,
+    // added to the built-in profiles
+    pub profiles: Vec<Profile>,
+    pub bindings: Vec<Binding>,
+    pub log_file: Option<PathBuf>,
+    pub library_dir: Option<PathBuf>,
+    pub http: Option<HttpSettings>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            delay: 40,
+            profile: Profile::default().name,
+            profiles: Vec::new(),
+            bindings: Vec::new(),
+            log_file: None,
+            library_dir: None,
+            http: None,
+        }
+    }
+}
+
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("auto_morph")
+}
+
+impl Settings {
+    pub fn path() -> PathBuf {
+        config_dir().join("settings.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| format!("Couldn't parse {}: {e}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Couldn't read {}: {e}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, text).map_err(|e| format!("Couldn't write {}: {e}", path.display()))
+    }
+
+    pub fn library_dir(&self) -> PathBuf {
+        self.library_dir
+            .clone()
+            .unwrap_or_else(|| config_dir().join("morphs"))
+    }
+
+    pub fn all_profiles(&self) -> Vec<Profile> {
+        let mut profiles = Profile::builtin();
+        profiles.extend(self.profiles.iter().cloned());
+        profiles
+    }
+
+    pub fn find_profile(&self, name: &str) -> Option<Profile> {
+        self.all_profiles()
+            .into_iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+    }
+}
@@ -1,88 +1,161 @@
-use copypasta::{ClipboardContext, ClipboardProvider};
+use crate::{
+    backend::{Backend, RecordingBackend, Step},
+    catalog::Catalog,
+    parser::{Script, parse},
+    profile::{Input, Mode, Profile},
+    reset::then_apply,
+};
 use eframe::egui;
-use enigo::{Direction, Enigo, Key, Keyboard, Settings};
-use std::sync::{Arc, Mutex};
-
-pub fn commands(cmds: &str, delay: u64, log: Arc<Mutex<Vec<String>>>, ctx: &egui::Context) {
-    let mut enigo = Enigo::new(&Settings::default()).unwrap();
-    let mut clipboard = ClipboardContext::new().expect("Failed to get clipboard context.");
-    let original_clipboard = clipboard.get_contents().ok();
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, Ordering},
+    mpsc,
+};
+
+// checked between keystrokes, a run stops at the next one once this is set
+pub static CANCEL: AtomicBool = AtomicBool::new(false);
+
+// (submission, total) of the run in progress
+pub static PROGRESS: Mutex<Option<(usize, usize)>> = Mutex::new(None);
+
+static LOG_SUBSCRIBERS: Mutex<Vec<mpsc::Sender<String>>> = Mutex::new(Vec::new());
+
+// `applied` holds the last morph sent, it's what `reset_first` undoes and this one replaces it
+#[allow(clippy::too_many_arguments)]
+pub fn commands(
+    cmds: &str,
+    profile: &Profile,
+    delay: u64,
+    catalog: &Catalog,
+    applied: &Mutex<Option<Script>>,
+    backend: &mut dyn Backend,
+    log: Arc<Mutex<Vec<String>>>,
+    ctx: &egui::Context,
+) {
+    let morph = parse(cmds);
+    let profile = morph.apply(profile);
+    let mut script = morph.clone();
+    if profile.reset_first && !script.is_run {
+        script = then_apply(&script, applied.lock().unwrap().as_ref(), &profile, catalog);
+        log_message(&log, "Resetting before the morph.", ctx);
+    }
+    if profile.mode == Mode::Chat {
+        log_message(&log, "Sending commands through chat.", ctx);
+    }
+    if profile.input == Input::Paste && !backend.clipboard_available() {
+        log_message(&log, "Clipboard unavailable, typing commands instead.", ctx);
+    }
 
-    if cmds.starts_with(":run") || cmds.starts_with("run") {
-        let mut command = cmds.trim();
-        if command.starts_with(":") {
-            command = &command[1..];
+    let mut cancelled = false;
+    let mut typed = false;
+    let submissions = script.submissions(&profile, catalog);
+    'submissions: for (index, command) in submissions.iter().enumerate() {
+        *PROGRESS.lock().unwrap() = Some((index + 1, submissions.len()));
+        let text = command.for_mode(profile.mode);
+        if script.is_run {
+            log_message(&log, &format!("Running run command: {text}"), ctx);
+        } else {
+            log_message(&log, &format!("Morphing line: {text}"), ctx);
         }
 
-        if !command.is_empty() {
-            log_message(&log, &format!("Running run command: {command}"), ctx);
-            #[cfg(target_os = "windows")]
-            {
-                enigo.raw(40, Direction::Click).unwrap()
+        // a bar left open with half a command in it would eat the player's next keystrokes
+        let mut open = false;
+        for step in steps(&text, &profile, delay) {
+            if CANCEL.load(Ordering::SeqCst) {
+                if open {
+                    backend.perform(&Step::Close);
+                }
+                cancelled = true;
+                break 'submissions;
             }
-            #[cfg(target_os = "macos")]
-            {
-                enigo.raw(39, Direction::Click).unwrap()
-            }
-            std::thread::sleep(std::time::Duration::from_millis(delay));
-            enigo.key(Key::Backspace, Direction::Click).unwrap();
-            std::thread::sleep(std::time::Duration::from_millis(delay / 2));
-            copy_paste(command, &mut enigo, &mut clipboard);
-            std::thread::sleep(std::time::Duration::from_millis(delay));
-            enigo.key(Key::Return, Direction::Click).unwrap();
-            std::thread::sleep(std::time::Duration::from_millis(delay));
+            backend.perform(&step);
+            typed = true;
+            open = (open || step.opens_input()) && step != Step::Enter;
         }
-
-        if let Some(original) = original_clipboard {
-            if clipboard.set_contents(original).is_ok() {
-                log_message(&log, "Clipboard restored.", ctx);
-            }
+        for message in backend.take_messages() {
+            log_message(&log, &message, ctx);
         }
-        log_message(&log, "Run command finished.", ctx);
-        return;
     }
 
-    for mut line in cmds.lines() {
-        if line.trim().is_empty() {
-            continue;
-        }
-
-        if line.starts_with(':') {
-            line = &line[1..];
-        }
-
-        log_message(&log, &format!("Morphing line: {line}"), ctx);
-
-        #[cfg(target_os = "windows")]
-        {
-            enigo.raw(40, Direction::Click).unwrap()
-        }
-        #[cfg(target_os = "macos")]
-        {
-            enigo.raw(39, Direction::Click).unwrap()
-        }
-        std::thread::sleep(std::time::Duration::from_millis(delay));
-
-        enigo.key(Key::Backspace, Direction::Click).unwrap();
-        std::thread::sleep(std::time::Duration::from_millis(delay / 2));
+    *PROGRESS.lock().unwrap() = None;
+    // a morph cut short still changed the avatar, the next reset undoes all of it
+    if typed && !morph.is_run {
+        *applied.lock().unwrap() = Some(morph);
+    }
 
-        copy_paste(line, &mut enigo, &mut clipboard);
+    let restored = backend.restore_clipboard();
+    for message in backend.take_messages() {
+        log_message(&log, &message, ctx);
+    }
+    if restored {
+        log_message(&log, "Clipboard restored.", ctx);
+    }
+    if cancelled {
+        log_message(&log, "Morph cancelled.", ctx);
+    } else if script.is_run {
+        log_message(&log, "Run command finished.", ctx);
+    } else {
+        log_message(&log, "Morph process finished.", ctx);
+    }
+}
 
-        std::thread::sleep(std::time::Duration::from_millis(delay));
+// runs the real flow against a recorder instead of the keyboard, `last` is the morph the
+// game has, if known
+pub fn preview(
+    cmds: &str,
+    profile: &Profile,
+    delay: u64,
+    catalog: &Catalog,
+    last: Option<Script>,
+) -> RecordingBackend {
+    let mut backend = RecordingBackend::default();
+    let log = Arc::new(Mutex::new(Vec::new()));
+    commands(
+        cmds,
+        profile,
+        delay,
+        catalog,
+        &Mutex::new(last),
+        &mut backend,
+        log,
+        &egui::Context::default(),
+    );
+    backend
+}
 
-        enigo.key(Key::Return, Direction::Click).unwrap();
-        std::thread::sleep(std::time::Duration::from_millis(delay));
-    }
+pub fn steps(text: &str, profile: &Profile, delay: u64) -> Vec<Step> {
+    let mut steps = match profile.mode {
+        Mode::CommandBar => vec![
+            Step::OpenCommandBar,
+            Step::Wait(delay),
+            Step::Erase,
+            Step::Wait(delay / 2),
+        ],
+        // the chat key opens chat without typing itself into the box
+        Mode::Chat => vec![Step::OpenChat, Step::Wait(delay)],
+    };
+    steps.push(match profile.input {
+        // typing presses Enter at every newline, a run script would go out in pieces
+        Input::Type if !text.contains('\n') => Step::Type(text.to_owned(), profile.char_delay),
+        _ => Step::Paste(text.to_owned()),
+    });
+    steps.extend([Step::Wait(delay), Step::Enter, Step::Wait(delay)]);
+    steps
+}
 
-    if let Some(original) = original_clipboard {
-        if clipboard.set_contents(original).is_ok() {
-            log_message(&log, "Clipboard restored.", ctx);
-        }
-    }
-    log_message(&log, "Morph process finished.", ctx);
+// every logged line is also sent to subscribers, for consoles without a window
+pub fn subscribe_log() -> mpsc::Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    LOG_SUBSCRIBERS.lock().unwrap().push(tx);
+    rx
 }
 
 pub fn log_message(log: &Arc<Mutex<Vec<String>>>, message: &str, ctx: &egui::Context) {
+    LOG_SUBSCRIBERS
+        .lock()
+        .unwrap()
+        .retain(|tx| tx.send(message.to_owned()).is_ok());
+
     let mut log_guard = log.lock().unwrap();
     log_guard.push(message.to_owned());
     if log_guard.len() > 100 {
@@ -91,20 +164,35 @@ pub fn log_message(log: &Arc<Mutex<Vec<String>>>, message: &str, ctx: &egui::Con
     ctx.request_repaint();
 }
 
-fn copy_paste(text: &str, enigo: &mut Enigo, clipboard: &mut ClipboardContext) {
-    clipboard.set_contents(text.to_owned()).unwrap();
-
-    #[cfg(target_os = "windows")]
-    {
-        enigo.key(Key::Control, Direction::Press).unwrap();
-        enigo.key(Key::V, Direction::Click).unwrap();
-        enigo.key(Key::Control, Direction::Release).unwrap();
-    }
-
-    #[cfg(target_os = "macos")]
-    {
-        enigo.key(Key::Meta, Direction::Press).unwrap();
-        enigo.key(Key::Other(0x00000009), Direction::Click).unwrap();
-        enigo.key(Key::Meta, Direction::Release).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_plans_a_batched_chat_morph() {
+        let profile = Profile {
+            mode: Mode::Chat,
+            batch: true,
+            ..Profile::default()
+        };
+        let recording = preview(
+            "hat me 1\nshirt me 2\n",
+            &profile,
+            40,
+            &Catalog::bundled(),
+            None,
+        );
+        assert_eq!(
+            recording.steps,
+            [
+                Step::OpenChat,
+                Step::Wait(40),
+                Step::Paste(":hat me 1 | :shirt me 2".to_owned()),
+                Step::Wait(40),
+                Step::Enter,
+                Step::Wait(40),
+            ]
+        );
+        assert_eq!(recording.total_ms(), 120);
     }
 }
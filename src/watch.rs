@@ -0,0 +1,87 @@
+use eframe::egui;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+#[derive(Default)]
+pub struct Changes {
+    pub file: bool,
+    pub library: bool,
+}
+
+// watches the open morph file and the library folder, repainting when either changes
+pub struct Watch {
+    watcher: RecommendedWatcher,
+    rx: mpsc::Receiver<PathBuf>,
+    library_dir: PathBuf,
+    file: Option<PathBuf>,
+    // the file's folder, when the library watch doesn't already cover it
+    file_dir: Option<PathBuf>,
+}
+
+impl Watch {
+    pub fn new(library_dir: PathBuf, ctx: egui::Context) -> Result<Self, String> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            for path in event.paths {
+                let _ = tx.send(path);
+            }
+            ctx.request_repaint();
+        })
+        .map_err(|e| e.to_string())?;
+
+        fs::create_dir_all(&library_dir).map_err(|e| e.to_string())?;
+        let library_dir = fs::canonicalize(&library_dir).map_err(|e| e.to_string())?;
+        watcher
+            .watch(&library_dir, RecursiveMode::Recursive)
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            watcher,
+            rx,
+            library_dir,
+            file: None,
+            file_dir: None,
+        })
+    }
+
+    // editors often save by replacing the file, so the folder is watched rather than the file
+    pub fn set_file(&mut self, path: &Path) {
+        if let Some(dir) = self.file_dir.take() {
+            let _ = self.watcher.unwatch(&dir);
+        }
+
+        self.file = fs::canonicalize(path).ok();
+        let library_dir = &self.library_dir;
+        let watcher = &mut self.watcher;
+        self.file_dir = self
+            .file
+            .as_ref()
+            .and_then(|file| file.parent())
+            .filter(|dir| !dir.starts_with(library_dir))
+            .filter(|dir| watcher.watch(dir, RecursiveMode::NonRecursive).is_ok())
+            .map(Path::to_path_buf);
+    }
+
+    pub fn poll(&self) -> Changes {
+        let mut changes = Changes::default();
+        for path in self.rx.try_iter() {
+            if self.file.as_ref() == Some(&path) {
+                changes.file = true;
+            }
+            if path.starts_with(&self.library_dir) {
+                changes.library = true;
+            }
+        }
+        changes
+    }
+}
@@ -1,5 +1,11 @@
+use crate::{dispatch::Job, settings::Binding, utils::log_message};
+use eframe::egui;
 use egui_keybinds::KeyCode as EKey;
-use rdev::Key as RKey;
+use rdev::{EventType, Key as RKey};
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex, mpsc},
+};
 
 pub fn key_to_string(key: &rdev::Key) -> String {
     format!("{key:?}")
@@ -107,3 +113,71 @@ pub fn egui_key_to_rdev_key(egui_key: EKey) -> Option<RKey> {
         _ => None,
     }
 }
+
+pub fn string_to_key(name: &str) -> Option<RKey> {
+    serde_json::from_value(serde_json::Value::String(name.to_owned())).ok()
+}
+
+pub fn listen_bindings(
+    bindings: &[Binding],
+    tx: mpsc::Sender<Job>,
+    log: Arc<Mutex<Vec<String>>>,
+    ctx: egui::Context,
+) -> Result<(), String> {
+    let mut targets = Vec::new();
+    for binding in bindings {
+        let keys = binding
+            .keys
+            .iter()
+            .map(|name| string_to_key(name).ok_or(format!("Unknown key {name}")))
+            .collect::<Result<HashSet<RKey>, String>>()?;
+        if keys.is_empty() {
+            return Err(format!(
+                "Binding for {} has no keys",
+                binding.morph.display()
+            ));
+        }
+        targets.push((keys, binding.morph.clone(), false));
+    }
+
+    std::thread::spawn(move || {
+        let callback_log = Arc::clone(&log);
+        let callback_ctx = ctx.clone();
+        let mut pressed_keys = HashSet::new();
+
+        let callback = move |event: rdev::Event| {
+            match event.event_type {
+                EventType::KeyPress(key) => {
+                    pressed_keys.insert(key);
+                }
+                EventType::KeyRelease(key) => {
+                    pressed_keys.remove(&key);
+                }
+                _ => return,
+            }
+            for (keys, morph, is_down) in targets.iter_mut() {
+                let is_met = keys.is_subset(&pressed_keys);
+                if is_met && !*is_down {
+                    log_message(
+                        &callback_log,
+                        &format!("Hotkey PRESSED for {}!", morph.display()),
+                        &callback_ctx,
+                    );
+                    let _ = tx.send(Job::File {
+                        path: morph.clone(),
+                        force: false,
+                    });
+                }
+                *is_down = is_met;
+            }
+        };
+        if let Err(error) = rdev::listen(callback) {
+            log_message(
+                &log,
+                &format!("Error listening to keyboard: {error:?}"),
+                &ctx,
+            );
+        }
+    });
+    Ok(())
+}